@@ -0,0 +1,76 @@
+#![feature(test)]
+
+extern crate lobotomy;
+extern crate test;
+
+use lobotomy::common::types::Level;
+use lobotomy::order_book::{ArrayLevels, L2Book, TopLevels, VecLevels};
+use rand::Rng;
+
+/// Random upserts and deletes over twice the depth of the book, a quarter of them are deletes
+fn prepare_history<const N: usize>() -> Vec<Level<i64, f64>> {
+    let history_len = 1_000_000;
+    let mut rng = rand::thread_rng();
+
+    (0..history_len)
+        .map(|_| Level {
+            px: rng.gen_range(0..2 * N as i64),
+            amt: if rng.gen_bool(0.25) {
+                0.0
+            } else {
+                rng.gen_range(1.0..100.0)
+            },
+            order_count: 0,
+        })
+        .collect()
+}
+
+fn bench_l2_book<const N: usize, T: TopLevels<i64, f64, N>>(b: &mut test::Bencher) {
+    let history = prepare_history::<N>();
+    let mut iter = history.iter().cycle();
+
+    let mut book = L2Book::<i64, f64, N, false, T>::new(1);
+    for px in 0..N as i64 {
+        book.upsert(Level::new(2 * px, 1.0));
+    }
+
+    b.iter(std::hint::black_box(|| {
+        let level = iter.next().unwrap();
+
+        if level.amt == 0.0 {
+            book.delete(level.px, |worst_px| Some(Level::new(worst_px + 1, 1.0)));
+        } else {
+            book.upsert(*level);
+        }
+    }));
+}
+
+#[bench]
+fn vec_l2_book_16_bench(b: &mut test::Bencher) {
+    bench_l2_book::<16, VecLevels<i64, f64>>(b);
+}
+
+#[bench]
+fn array_l2_book_16_bench(b: &mut test::Bencher) {
+    bench_l2_book::<16, ArrayLevels<i64, f64, 16>>(b);
+}
+
+#[bench]
+fn vec_l2_book_256_bench(b: &mut test::Bencher) {
+    bench_l2_book::<256, VecLevels<i64, f64>>(b);
+}
+
+#[bench]
+fn array_l2_book_256_bench(b: &mut test::Bencher) {
+    bench_l2_book::<256, ArrayLevels<i64, f64, 256>>(b);
+}
+
+#[bench]
+fn vec_l2_book_16384_bench(b: &mut test::Bencher) {
+    bench_l2_book::<16384, VecLevels<i64, f64>>(b);
+}
+
+#[bench]
+fn array_l2_book_16384_bench(b: &mut test::Bencher) {
+    bench_l2_book::<16384, ArrayLevels<i64, f64, 16384>>(b);
+}
@@ -0,0 +1,173 @@
+#![feature(test)]
+
+extern crate lobotomy;
+extern crate test;
+
+use lobotomy::common::types::L2Delta;
+use lobotomy::nasdaq::{ItchIntoL2Deltas, ItchIntoL3Deltas, Price4Wrapper};
+use lobotomy::order_book::{L2BookBuilder, OrderBook, OrderStore};
+
+use itchy::{AddOrder, ArrayString, Body, Message, Price4, ReplaceOrder, Side};
+use rand::Rng;
+
+const LOB_SIZE: usize = 2_usize.pow(14);
+const TICK_SIZE: u32 = 100;
+const MID_PX: u32 = 1_000_000;
+
+fn message(body: Body) -> Message {
+    Message {
+        tag: 0,
+        stock_locate: 0,
+        tracking_number: 0,
+        timestamp: 0,
+        body,
+    }
+}
+
+/// Random ITCH order flow around `MID_PX`, every order is removed by the end of the history
+fn prepare_history() -> Vec<Message> {
+    let history_len = 1_000_000;
+    let mut rng = rand::thread_rng();
+
+    let mut history = Vec::with_capacity(history_len);
+    let mut live: Vec<(u64, Side, u32)> = Vec::new();
+    let mut next_reference = 1;
+
+    let random_px = |rng: &mut rand::rngs::ThreadRng, side: Side| {
+        let ticks = rng.gen_range(1..512) * TICK_SIZE;
+        match side {
+            Side::Buy => Price4::from(MID_PX - ticks),
+            Side::Sell => Price4::from(MID_PX + ticks),
+        }
+    };
+
+    while history.len() < history_len {
+        let action = rng.gen_range(0..100);
+
+        if live.is_empty() || action < 45 {
+            let side = if rng.gen_bool(0.5) {
+                Side::Buy
+            } else {
+                Side::Sell
+            };
+            let shares = rng.gen_range(1..1000);
+
+            history.push(message(Body::AddOrder(AddOrder {
+                reference: next_reference,
+                side,
+                shares,
+                stock: ArrayString::from("AAPL").unwrap(),
+                price: random_px(&mut rng, side),
+                mpid: None,
+            })));
+
+            live.push((next_reference, side, shares));
+            next_reference += 1;
+            continue;
+        }
+
+        let pos = rng.gen_range(0..live.len());
+        let (reference, side, shares) = live[pos];
+
+        if action < 80 {
+            let reduced = rng.gen_range(1..=shares);
+
+            history.push(message(if action < 65 {
+                Body::OrderExecuted {
+                    reference,
+                    executed: reduced,
+                    match_number: 0,
+                }
+            } else {
+                Body::OrderCancelled {
+                    reference,
+                    cancelled: reduced,
+                }
+            }));
+
+            if reduced == shares {
+                live.swap_remove(pos);
+            } else {
+                live[pos].2 -= reduced;
+            }
+        } else if action < 90 {
+            history.push(message(Body::DeleteOrder { reference }));
+            live.swap_remove(pos);
+        } else {
+            let shares = rng.gen_range(1..1000);
+
+            history.push(message(Body::ReplaceOrder(ReplaceOrder {
+                old_reference: reference,
+                new_reference: next_reference,
+                shares,
+                price: random_px(&mut rng, side),
+            })));
+
+            live[pos] = (next_reference, side, shares);
+            next_reference += 1;
+        }
+    }
+
+    for (reference, _, _) in live {
+        history.push(message(Body::DeleteOrder { reference }));
+    }
+
+    history
+}
+
+fn new_lob() -> (
+    L2BookBuilder<Price4Wrapper, u32, LOB_SIZE, true>,
+    L2BookBuilder<Price4Wrapper, u32, LOB_SIZE, false>,
+) {
+    let start_px = Price4Wrapper(Price4::from(0));
+    let end_px = None;
+    let tick_size = Price4Wrapper(Price4::from(TICK_SIZE));
+
+    (
+        L2BookBuilder::new(start_px, end_px, tick_size, None),
+        L2BookBuilder::new(start_px, end_px, tick_size, None),
+    )
+}
+
+/// The path used by `nasdaq_robot`: `ItchIntoL2Deltas` + one `apply_l2_deltas` per delta
+#[bench]
+fn itch_l2_closure_bench(b: &mut test::Bencher) {
+    let history = prepare_history();
+    let (mut bid, mut ask) = new_lob();
+    let mut l2_from_itch = ItchIntoL2Deltas::with_capacity(2 * history.len());
+
+    b.iter(std::hint::black_box(|| {
+        for msg in history.iter() {
+            l2_from_itch.apply_message(msg, |side, px, amt_delta, order_count_delta| {
+                let delta = L2Delta {
+                    px: Price4Wrapper(*px),
+                    amt_delta: *amt_delta,
+                    order_count_delta: *order_count_delta,
+                };
+
+                match side {
+                    Side::Buy => bid.apply_l2_deltas(std::slice::from_ref(&delta)),
+                    Side::Sell => ask.apply_l2_deltas(std::slice::from_ref(&delta)),
+                }
+            });
+        }
+    }));
+}
+
+#[bench]
+fn itch_apply_l3_deltas_bench(b: &mut test::Bencher) {
+    let history = prepare_history();
+    let tick_size = Price4Wrapper(Price4::from(TICK_SIZE));
+    let mut lob =
+        OrderBook::<_, _, LOB_SIZE>::new(Price4Wrapper(Price4::from(0)), None, tick_size, None);
+    let l3_from_itch = ItchIntoL3Deltas::new();
+    let mut orders = OrderStore::new(2 * history.len());
+
+    b.iter(std::hint::black_box(|| {
+        for msg in history.iter() {
+            l3_from_itch.apply_message(msg, |l3_delta| {
+                lob.apply_l3_deltas(&mut orders, std::slice::from_ref(l3_delta));
+            });
+        }
+    }));
+}
@@ -3,7 +3,7 @@
 extern crate lobotomy;
 extern crate test;
 
-use lobotomy::order_book::PriceMap;
+use lobotomy::order_book::{DenseLevels, LevelStorage, PagedLevels, PriceMap};
 use rand::Rng;
 
 const TICK_SIZE: f64 = 0.1;
@@ -30,15 +30,15 @@ fn prepare_history() -> Vec<(f64, f64)> {
     history
 }
 
-#[bench]
-fn price_map_bench(b: &mut test::Bencher) {
+fn bench_price_map<S: LevelStorage<f64>>(b: &mut test::Bencher) {
     let history = prepare_history();
     let mut iter = history.iter().cycle();
 
-    let mut fast_map = PriceMap::new(
+    let mut fast_map = PriceMap::<f64, f64, S>::new(
         round_to_tick(rand::thread_rng().gen_range(PX_RANGE)),
         None,
         TICK_SIZE,
+        None,
     );
 
     b.iter(std::hint::black_box(|| {
@@ -47,6 +47,16 @@ fn price_map_bench(b: &mut test::Bencher) {
     }));
 }
 
+#[bench]
+fn price_map_bench(b: &mut test::Bencher) {
+    bench_price_map::<DenseLevels<f64>>(b);
+}
+
+#[bench]
+fn paged_price_map_bench(b: &mut test::Bencher) {
+    bench_price_map::<PagedLevels<f64>>(b);
+}
+
 #[bench]
 fn naive_map_bench(b: &mut test::Bencher) {
     let history = prepare_history();
@@ -59,3 +69,82 @@ fn naive_map_bench(b: &mut test::Bencher) {
         naive_map.insert((*px / TICK_SIZE).round() as usize, *amt);
     }));
 }
+
+// A deep book with a handful of levels far apart: refilling the top after a delete
+// has to skip over thousands of empty ticks.
+const SPARSE_LEVELS: usize = 64;
+const SPARSE_GAP: usize = 10_000;
+
+fn sparse_levels() -> Vec<f64> {
+    (0..SPARSE_LEVELS)
+        .map(|i| round_to_tick(PX_RANGE.start + (i * SPARSE_GAP) as f64 * TICK_SIZE))
+        .collect()
+}
+
+fn bench_sparse_next_px<S: LevelStorage<f64>>(b: &mut test::Bencher) {
+    let levels = sparse_levels();
+    let mut iter = levels.iter().cycle();
+
+    let mut fast_map = PriceMap::<f64, f64, S>::new(PX_RANGE.start, None, TICK_SIZE, None);
+    for px in levels.iter() {
+        fast_map.get_mut(*px).amt = 1.0;
+    }
+
+    b.iter(std::hint::black_box(|| {
+        let px = iter.next().unwrap();
+        fast_map.next_px::<false>(px)
+    }));
+}
+
+#[bench]
+fn sparse_next_px_bench(b: &mut test::Bencher) {
+    bench_sparse_next_px::<DenseLevels<f64>>(b);
+}
+
+#[bench]
+fn paged_sparse_next_px_bench(b: &mut test::Bencher) {
+    bench_sparse_next_px::<PagedLevels<f64>>(b);
+}
+
+#[bench]
+fn sparse_naive_next_px_bench(b: &mut test::Bencher) {
+    let levels = sparse_levels();
+    let mut iter = levels.iter().cycle();
+
+    let mut naive_levels = vec![0.0; SPARSE_LEVELS * SPARSE_GAP];
+    for i in 0..SPARSE_LEVELS {
+        naive_levels[i * SPARSE_GAP] = 1.0;
+    }
+
+    b.iter(std::hint::black_box(|| {
+        let px = iter.next().unwrap();
+        let idx = ((*px - PX_RANGE.start) / TICK_SIZE).round() as usize;
+        naive_levels[idx + 1..]
+            .iter()
+            .position(|amt| *amt != 0.0)
+            .map(|offset| idx + 1 + offset)
+    }));
+}
+
+// A fine tick size and a print far below the range: the dense map copies the whole range,
+// the paged one only extends its page table.
+const FINE_TICK_SIZE: f64 = 0.0001;
+
+fn bench_far_low_px<S: LevelStorage<f64>>(b: &mut test::Bencher) {
+    b.iter(std::hint::black_box(|| {
+        let mut fast_map = PriceMap::<f64, f64, S>::new(100.0, None, FINE_TICK_SIZE, None);
+        fast_map.get_mut(200.0).amt = 1.0;
+        fast_map.get_mut(1.0).amt = 1.0;
+        fast_map.get_immut(1.0).amt
+    }));
+}
+
+#[bench]
+fn far_low_px_bench(b: &mut test::Bencher) {
+    bench_far_low_px::<DenseLevels<f64>>(b);
+}
+
+#[bench]
+fn paged_far_low_px_bench(b: &mut test::Bencher) {
+    bench_far_low_px::<PagedLevels<f64>>(b);
+}
@@ -1,13 +1,17 @@
 extern crate lobotomy;
 
-use lobotomy::binance::{DepthDiffDecoder, MarketData, RestoreManager};
+use lobotomy::binance::{BinanceDecimal, DepthDiffDecoder, MarketData, RestoreManager};
 use lobotomy::common::communication::EventMessage;
-use lobotomy::common::WebSocketListener;
-use lobotomy::order_book::L2BookBuilder;
+use lobotomy::common::{TickSchedule, WebSocketListener};
+use lobotomy::order_book::{BookState, OrderBook};
 
 use heapless::spsc; // std::sync::mpsc was causing a segfault
 
 const QUEUE_SIZE: usize = 16;
+const LOB_SIZE: usize = 2_usize.pow(14);
+const MAX_TICKS: usize = 2_usize.pow(20);
+
+type BinanceLOB = OrderBook<BinanceDecimal, BinanceDecimal, LOB_SIZE>;
 
 fn init_log() {
     fast_log::init(
@@ -40,17 +44,20 @@ fn calibrate_tick_counter() -> f64 {
     counter_accuracy
 }
 
+/// The price map window is centered on the best price of the first snapshot,
+/// so the book doesn't start far below the market and re-center right away
+fn new_lob(best_px: BinanceDecimal) -> BinanceLOB {
+    let tick_schedule = TickSchedule::new("0.01".parse::<BinanceDecimal>().unwrap());
+    let start_tick_idx = tick_schedule.px_to_tick_idx(&best_px) - (MAX_TICKS / 2) as i64;
+    let start_px = tick_schedule.tick_idx_to_px(&start_tick_idx);
+
+    OrderBook::new(start_px, None, tick_schedule, Some(MAX_TICKS))
+}
+
 fn limit_order_book_task(mut md_receiver: spsc::Consumer<EventMessage<MarketData>, QUEUE_SIZE>) {
     let counter_accuracy = calibrate_tick_counter();
 
-    let start_px = 0.0;
-    let end_px = None;
-    let tick_size = 0.01;
-    const LOB_SIZE: usize = 2_usize.pow(14);
-    let mut bid_lob_builder =
-        L2BookBuilder::<f64, f64, LOB_SIZE, true>::new(start_px, end_px, tick_size);
-    let mut ask_lob_builder =
-        L2BookBuilder::<f64, f64, LOB_SIZE, false>::new(start_px, end_px, tick_size);
+    let mut lob: Option<BinanceLOB> = None;
 
     loop {
         let msg = match md_receiver.dequeue() {
@@ -64,32 +71,59 @@ fn limit_order_book_task(mut md_receiver: spsc::Consumer<EventMessage<MarketData
             EventMessage::Event(md) => {
                 let tick0 = tick_counter::start();
                 let num_updates = match &md {
-                    MarketData::Diff(diff) => {
-                        bid_lob_builder.apply_l2_upserts(&diff.bids);
-                        ask_lob_builder.apply_l2_upserts(&diff.asks);
-
-                        diff.bids.len() + diff.asks.len()
-                    }
+                    MarketData::Diff(diff) => match lob.as_mut() {
+                        Some(lob) => {
+                            lob.apply_l2_upserts(&diff.bids, &diff.asks);
+
+                            diff.bids.len() + diff.asks.len()
+                        }
+                        None => {
+                            log::warn!("Diff before the first snapshot, skipped");
+                            continue;
+                        }
+                    },
                     MarketData::Snapshot(snapshot) => {
-                        bid_lob_builder.apply_l2_snapshot(&snapshot.bids);
-                        ask_lob_builder.apply_l2_snapshot(&snapshot.asks);
+                        let best_px = snapshot
+                            .bids
+                            .first()
+                            .or(snapshot.asks.first())
+                            .map_or(BinanceDecimal::ZERO, |lvl| lvl.px);
+
+                        lob.get_or_insert_with(|| new_lob(best_px))
+                            .apply_l2_snapshot(&snapshot.bids, &snapshot.asks);
 
                         snapshot.bids.len() + snapshot.asks.len()
                     }
                 };
                 let tick1 = tick_counter::stop();
+                let lob = lob.as_ref().unwrap();
 
-                let bids = bid_lob_builder.book().levels();
-                let asks = ask_lob_builder.book().levels();
+                let (bid, ask) = match (lob.best_bid(), lob.best_ask()) {
+                    (Some(bid), Some(ask)) => (bid, ask),
+                    _ => {
+                        log::warn!("Book is not two-sided: state=[{:?}]", lob.state());
+                        continue;
+                    }
+                };
 
                 log::info!(
-                    "latency=[{}], bids=[{}], asks=[{}]",
+                    "latency=[{}], bids=[{}@{}], asks=[{}@{}], microprice=[{:.4}]",
                     ((tick1 - tick0) as f64 * counter_accuracy).round() as usize / num_updates,
-                    bids[0],
-                    asks[0]
+                    bid.amt,
+                    bid.px,
+                    ask.amt,
+                    ask.px,
+                    lob.microprice().unwrap_or_default()
                 );
 
-                assert!(bids[0] < asks[0]);
+                if lob.state() != BookState::Normal {
+                    log::warn!(
+                        "Book is {:?}: bid=[{}], ask=[{}]",
+                        lob.state(),
+                        bid.px,
+                        ask.px
+                    );
+                }
             }
             EventMessage::Stop => break,
         }
@@ -0,0 +1,106 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::Level;
+use lobotomy::order_book::L2Book;
+
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+const HISTORY_LEN: usize = 1_000_000;
+const QUANTILES: [f64; 7] = [0.5, 0.6, 0.7, 0.8, 0.9, 0.95, 0.99];
+
+/// Serialized tick counter. The asm of `tick_counter` doesn't declare `rdx` as clobbered on x86-64
+#[inline(always)]
+fn ticks() -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        std::arch::x86_64::_mm_lfence();
+        let ticks = std::arch::x86_64::_rdtsc();
+        std::arch::x86_64::_mm_lfence();
+        ticks
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        tick_counter::start()
+    }
+}
+
+/// Nanoseconds per tick, measured against the monotonic clock
+fn calibrate_ticks() -> f64 {
+    let measure_duration = Duration::from_millis(500);
+
+    let (time0, tick0) = (Instant::now(), ticks());
+    std::thread::sleep(measure_duration);
+    let (time1, tick1) = (Instant::now(), ticks());
+
+    (time1 - time0).as_nanos() as f64 / (tick1 - tick0) as f64
+}
+
+/// Random upserts and deletes over twice the depth of the book, a quarter of them are deletes
+fn prepare_history<const N: usize>() -> Vec<Level<i64, f64>> {
+    let mut rng = rand::thread_rng();
+
+    (0..HISTORY_LEN)
+        .map(|_| Level {
+            px: rng.gen_range(0..2 * N as i64),
+            amt: if rng.gen_bool(0.25) {
+                0.0
+            } else {
+                rng.gen_range(1.0..100.0)
+            },
+            order_count: 0,
+        })
+        .collect()
+}
+
+/// Latency quantiles of one update of a book of depth `N`, nanoseconds
+fn measure<const N: usize>(counter_accuracy: f64) -> Vec<usize> {
+    let history = prepare_history::<N>();
+
+    let mut book = L2Book::<i64, f64, N, false>::new(1);
+    for px in 0..N as i64 {
+        book.upsert(Level::new(2 * px, 1.0));
+    }
+
+    let mut latencies = history
+        .iter()
+        .map(|level| {
+            let tick0 = ticks();
+
+            if level.amt == 0.0 {
+                book.delete(level.px, |worst_px| Some(Level::new(worst_px + 1, 1.0)));
+            } else {
+                book.upsert(*level);
+            }
+
+            let tick1 = ticks();
+            ((tick1 - tick0) as f64 * counter_accuracy).round() as usize
+        })
+        .collect::<Vec<_>>();
+
+    latencies.sort_unstable();
+
+    QUANTILES
+        .iter()
+        .map(|q| latencies[((latencies.len() - 1) as f64 * q) as usize])
+        .collect()
+}
+
+fn main() {
+    let counter_accuracy = calibrate_ticks();
+
+    let columns = [
+        measure::<16>(counter_accuracy),
+        measure::<256>(counter_accuracy),
+        measure::<16384>(counter_accuracy),
+    ];
+
+    println!("| Q              | depth=16 | depth=256 | depth=16384 |");
+    println!("| :------------- | :-----: | :-----: | :-----: |");
+    for (i, q) in QUANTILES.iter().enumerate() {
+        println!(
+            "| {:<14} |   {}ns  |   {}ns  |   {}ns  |",
+            q, columns[0][i], columns[1][i], columns[2][i]
+        );
+    }
+}
@@ -1,13 +1,12 @@
 extern crate lobotomy;
 
 use lobotomy::common::communication::EventMessage;
-use lobotomy::common::types::L2Delta;
+use lobotomy::common::types::{L2Delta, Side};
 use lobotomy::common::StackInvocable;
 use lobotomy::nasdaq::{ItchIntoL2Deltas, Price4Wrapper};
-use lobotomy::order_book::L2BookBuilder;
+use lobotomy::order_book::{ConflatedBook, OrderBook};
 
 use itchy::Body;
-use more_asserts::assert_lt;
 use rtrb::{Consumer, Producer, PushError, RingBuffer};
 
 pub type Invocable = StackInvocable<32>;
@@ -51,11 +50,10 @@ fn async_task(mut async_consuemr: Consumer<EventMessage<Invocable>>) {
 fn limit_order_book_task(mut async_producer: Producer<EventMessage<Invocable>>) {
     const LOB_SIZE: usize = 2_usize.pow(14);
 
-    #[derive(Clone)]
-    struct StockLOB {
-        bid: L2BookBuilder<Price4Wrapper, u32, LOB_SIZE, true>,
-        ask: L2BookBuilder<Price4Wrapper, u32, LOB_SIZE, false>,
-    }
+    /// Distinct levels touched by one transaction of a stock
+    const TRANSACTION_SIZE: usize = 16;
+
+    type StockLOB = ConflatedBook<Price4Wrapper, u32, LOB_SIZE>;
 
     let counter_accuracy = calibrate_tick_counter();
 
@@ -69,60 +67,106 @@ fn limit_order_book_task(mut async_producer: Producer<EventMessage<Invocable>>)
     ];
 
     let mut l2_from_itch = ItchIntoL2Deltas::new();
-    let mut stock_to_lob = vec![None; 2_usize.pow(14)];
+    let mut stock_to_lob: Vec<Option<StockLOB>> = vec![None; 2_usize.pow(14)];
+
+    // Messages of one sweep share the timestamp, across stocks too: the books are observed once
+    // all of them are applied, the latency is the one of the whole transaction.
+    // A file has no packets, so a transaction ends on a new timestamp (see `ConflatedBook::start_at`)
+    // or at the end of the feed.
+    let mut pending: Vec<usize> = Vec::new();
+    let mut tick0 = tick_counter::start();
+
+    let mut publish_latency = |tick0: u64| {
+        let tick1 = tick_counter::start();
+
+        let async_task = Invocable::new(move || {
+            println!(
+                "latency=[{}]",
+                ((tick1 - tick0) as f64 * counter_accuracy).round() as usize,
+            );
+        });
+
+        let mut item = EventMessage::Event(async_task);
+        while let Err(PushError::Full(i)) = async_producer.push(item) {
+            // println!("MarketData queue is full!");
+            item = i;
+            continue;
+        }
+    };
 
     for msg in stream {
         let msg = msg.unwrap();
 
+        let mut had_updates = false;
+        pending.retain(|stock_locate| match stock_to_lob[*stock_locate].as_mut() {
+            Some(lob) => {
+                had_updates |= lob.start_at(msg.timestamp);
+                lob.has_pending()
+            }
+            None => false,
+        });
+
+        if had_updates {
+            publish_latency(tick0);
+        }
+        if pending.is_empty() {
+            tick0 = tick_counter::start();
+        }
+
         if let Body::StockDirectory(sd) = &msg.body {
             let stock = sd.stock.trim_end();
             if stock_filter.contains(&stock) {
                 let start_px = Price4Wrapper(itchy::Price4::from(0));
                 let end_px = None;
-                let tick_size = Price4Wrapper(itchy::Price4::from(100));
+                let tick_schedule = Price4Wrapper::tick_schedule();
+                let max_ticks = None;
 
                 stock_to_lob.insert(
                     msg.stock_locate as usize,
-                    Some(StockLOB {
-                        bid: L2BookBuilder::new(start_px, end_px, tick_size),
-                        ask: L2BookBuilder::new(start_px, end_px, tick_size),
-                    }),
+                    Some(ConflatedBook::new(
+                        OrderBook::<Price4Wrapper, u32, LOB_SIZE>::new(
+                            start_px,
+                            end_px,
+                            tick_schedule,
+                            max_ticks,
+                        ),
+                        TRANSACTION_SIZE,
+                    )),
                 )
             }
         };
 
-        let mut had_updates = false;
-        let tick0 = tick_counter::start();
         // ---------------------------------------------------------------------
-        l2_from_itch.apply_message(&msg, |side, px, amt_delta| {
-            if let Some(lob) = stock_to_lob[msg.stock_locate as usize].as_mut() {
-                had_updates = true;
-                match side {
-                    itchy::Side::Buy => {
-                        lob.bid.apply_l2_deltas(std::slice::from_ref(&L2Delta {
-                            px: Price4Wrapper(*px),
-                            amt_delta: *amt_delta,
-                        }));
-                    }
-                    itchy::Side::Sell => {
-                        lob.ask.apply_l2_deltas(std::slice::from_ref(&L2Delta {
+        match stock_to_lob[msg.stock_locate as usize].as_mut() {
+            Some(lob) => {
+                // The previous transaction of the book is committed above, if any
+                lob.start_at(msg.timestamp);
+                let was_pending = lob.has_pending();
+
+                l2_from_itch.apply_message(&msg, |side, px, amt_delta, order_count_delta| {
+                    let side = match side {
+                        itchy::Side::Buy => Side::Bid,
+                        itchy::Side::Sell => Side::Ask,
+                    };
+
+                    lob.push(
+                        &side,
+                        &L2Delta {
                             px: Price4Wrapper(*px),
                             amt_delta: *amt_delta,
-                        }));
-                    }
-                }
+                            order_count_delta: *order_count_delta,
+                        },
+                    );
+                });
 
-                // let b0 = match lob.bid.book().levels().get(0) {
-                //     Some(b0) => *b0,
-                //     None => Price4Wrapper::default(),
-                // };
+                if !was_pending && lob.has_pending() {
+                    pending.push(msg.stock_locate as usize);
+                }
 
-                // let a0 = match lob.ask.book().levels().get(0) {
-                //     Some(a0) => *a0,
-                //     None => Price4Wrapper::default(),
-                // };
+                // let b0 = lob.book().best_bid().map(|lvl| lvl.px).unwrap_or_default();
+                // let a0 = lob.book().best_ask().map(|lvl| lvl.px).unwrap_or_default();
 
-                // assert_lt!(b0, a0);
+                // assert_ne!(lob.book().state(), BookState::Crossed);
 
                 // let async_task = Invocable::new(move || {
                 //     println!(
@@ -141,27 +185,20 @@ fn limit_order_book_task(mut async_producer: Producer<EventMessage<Invocable>>)
                 //     continue;
                 // }
             }
-        });
+            None => l2_from_itch.apply_message(&msg, |_, _, _, _| {}),
+        }
         // ---------------------------------------------------------------------
-        let tick1 = tick_counter::start();
+    }
 
-        if !had_updates {
-            continue;
+    let mut had_updates = false;
+    for stock_locate in pending.drain(..) {
+        if let Some(lob) = stock_to_lob[stock_locate].as_mut() {
+            had_updates |= lob.commit();
         }
+    }
 
-        let async_task = Invocable::new(move || {
-            println!(
-                "latency=[{}]",
-                ((tick1 - tick0) as f64 * counter_accuracy).round() as usize,
-            );
-        });
-
-        let mut item = EventMessage::Event(async_task);
-        while let Err(PushError::Full(i)) = async_producer.push(item) {
-            // println!("MarketData queue is full!");
-            item = i;
-            continue;
-        }
+    if had_updates {
+        publish_latency(tick0);
     }
 
     while let Err(_) = async_producer.push(EventMessage::Stop) {}
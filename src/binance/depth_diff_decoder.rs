@@ -1,4 +1,6 @@
+use super::BinanceDecimal;
 use crate::common::types::Level;
+use crate::common::ParseDecimalError;
 
 use serde::Deserialize;
 
@@ -22,10 +24,11 @@ pub struct DepthDiff {
     pub symbol: String,
     pub first_update_id: u64,
     pub last_update_id: u64,
-    pub bids: Vec<Level<f64, f64>>,
-    pub asks: Vec<Level<f64, f64>>,
+    pub bids: Vec<Level<BinanceDecimal, BinanceDecimal>>,
+    pub asks: Vec<Level<BinanceDecimal, BinanceDecimal>>,
 }
 
+#[derive(Default)]
 pub struct DepthDiffDecoder {}
 
 impl DepthDiffDecoder {
@@ -36,13 +39,10 @@ impl DepthDiffDecoder {
     pub fn decode(&self, text: &str) -> Result<DepthDiff, Box<dyn Error>> {
         let raw: RawDepthDiff = serde_json::from_str(text)?;
 
-        let str_to_f64 = |v: &Vec<(String, String)>| {
+        let str_to_levels = |v: &Vec<(String, String)>| {
             v.iter()
-                .map(|(px_str, amt_str)| Level {
-                    px: px_str.parse().unwrap(),
-                    amt: amt_str.parse().unwrap(),
-                })
-                .collect()
+                .map(|(px_str, amt_str)| Ok(Level::new(px_str.parse()?, amt_str.parse()?)))
+                .collect::<Result<_, ParseDecimalError>>()
         };
 
         Ok(DepthDiff {
@@ -50,8 +50,8 @@ impl DepthDiffDecoder {
             symbol: raw.s,
             first_update_id: raw.U,
             last_update_id: raw.u,
-            bids: str_to_f64(&raw.b),
-            asks: str_to_f64(&raw.a),
+            bids: str_to_levels(&raw.b)?,
+            asks: str_to_levels(&raw.a)?,
         })
     }
 }
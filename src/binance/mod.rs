@@ -3,3 +3,8 @@ mod restore_manager;
 
 pub use depth_diff_decoder::DepthDiffDecoder;
 pub use restore_manager::{MarketData, RestoreManager};
+
+use crate::common::Decimal;
+
+/// Binance sends prices and quantities with 8 decimals
+pub type BinanceDecimal = Decimal<8>;
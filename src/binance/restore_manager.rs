@@ -1,5 +1,7 @@
 use super::depth_diff_decoder::DepthDiff;
+use super::BinanceDecimal;
 use crate::common::types::Level;
+use crate::common::ParseDecimalError;
 
 use serde::Deserialize;
 
@@ -16,8 +18,8 @@ struct RawDepthSnapshot {
 #[derive(Debug)]
 pub struct DepthSnapshot {
     pub last_update_id: u64,
-    pub bids: Vec<Level<f64, f64>>,
-    pub asks: Vec<Level<f64, f64>>,
+    pub bids: Vec<Level<BinanceDecimal, BinanceDecimal>>,
+    pub asks: Vec<Level<BinanceDecimal, BinanceDecimal>>,
 }
 
 #[derive(Debug)]
@@ -103,19 +105,16 @@ impl RestoreManager {
 
         let raw: RawDepthSnapshot = serde_json::from_str(&text).unwrap();
 
-        let str_to_f64 = |v: &Vec<(String, String)>| {
+        let str_to_levels = |v: &Vec<(String, String)>| {
             v.iter()
-                .map(|(px_str, amt_str)| Level {
-                    px: px_str.parse().unwrap(),
-                    amt: amt_str.parse().unwrap(),
-                })
-                .collect()
+                .map(|(px_str, amt_str)| Ok(Level::new(px_str.parse()?, amt_str.parse()?)))
+                .collect::<Result<_, ParseDecimalError>>()
         };
 
         Ok(DepthSnapshot {
             last_update_id: raw.lastUpdateId,
-            bids: str_to_f64(&raw.bids),
-            asks: str_to_f64(&raw.asks),
+            bids: str_to_levels(&raw.bids)?,
+            asks: str_to_levels(&raw.asks)?,
         })
     }
 }
@@ -0,0 +1,241 @@
+use super::simd;
+use super::types::{Amount, Level, Price, TickSized};
+
+use num_traits::Zero;
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Fixed-point decimal: `mantissa * 10^-SCALE`.
+///
+/// The scale is a part of the type, so values of an instrument share it and the arithmetic
+/// and comparisons are plain integer operations. Parsing and formatting are exact.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal<const SCALE: u32>(i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseDecimalError {
+    Empty,
+    InvalidDigit,
+    /// More significant fractional digits than the scale
+    Precision,
+    Overflow,
+}
+
+impl<const SCALE: u32> Decimal<SCALE> {
+    pub const ZERO: Self = Decimal(0);
+    pub const ONE: Self = Decimal(10_i64.pow(SCALE));
+
+    #[inline(always)]
+    pub const fn from_mantissa(mantissa: i64) -> Self {
+        Decimal(mantissa)
+    }
+
+    #[inline(always)]
+    pub const fn mantissa(&self) -> i64 {
+        self.0
+    }
+}
+
+impl<const SCALE: u32> FromStr for Decimal<SCALE> {
+    type Err = ParseDecimalError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (is_negative, digits) = match s.as_bytes().first() {
+            Some(b'-') => (true, &s[1..]),
+            Some(b'+') => (false, &s[1..]),
+            _ => (false, s),
+        };
+
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return Err(ParseDecimalError::Empty);
+        }
+
+        let mut mantissa: i64 = 0;
+        let mut push_digit = |c: u8| -> Result<(), ParseDecimalError> {
+            if !c.is_ascii_digit() {
+                return Err(ParseDecimalError::InvalidDigit);
+            }
+
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add((c - b'0') as i64))
+                .ok_or(ParseDecimalError::Overflow)?;
+
+            Ok(())
+        };
+
+        for c in int_part.bytes() {
+            push_digit(c)?;
+        }
+
+        for (idx, c) in frac_part.bytes().enumerate() {
+            if idx < SCALE as usize {
+                push_digit(c)?;
+            } else if c != b'0' {
+                return Err(if c.is_ascii_digit() {
+                    ParseDecimalError::Precision
+                } else {
+                    ParseDecimalError::InvalidDigit
+                });
+            }
+        }
+
+        for _ in frac_part.len()..SCALE as usize {
+            push_digit(b'0')?;
+        }
+
+        Ok(Decimal(if is_negative { -mantissa } else { mantissa }))
+    }
+}
+
+/// Trailing fractional zeros are trimmed, e.g. `"0.01000000"` is printed as `0.01`.
+impl<const SCALE: u32> Display for Decimal<SCALE> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let scale = 10_u64.pow(SCALE);
+        let abs = self.0.unsigned_abs();
+        let sign = if self.0 < 0 { "-" } else { "" };
+
+        let mut frac = abs % scale;
+        if frac == 0 {
+            return write!(f, "{}{}", sign, abs / scale);
+        }
+
+        let mut width = SCALE as usize;
+        while frac.is_multiple_of(10) {
+            frac /= 10;
+            width -= 1;
+        }
+
+        write!(f, "{}{}.{:0width$}", sign, abs / scale, frac, width = width)
+    }
+}
+
+impl Display for ParseDecimalError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let reason = match self {
+            ParseDecimalError::Empty => "empty string",
+            ParseDecimalError::InvalidDigit => "invalid digit",
+            ParseDecimalError::Precision => "too many fractional digits",
+            ParseDecimalError::Overflow => "number too large",
+        };
+
+        write!(f, "Could not parse decimal: {}", reason)
+    }
+}
+
+impl Error for ParseDecimalError {}
+
+impl<const SCALE: u32> From<Decimal<SCALE>> for f64 {
+    fn from(value: Decimal<SCALE>) -> Self {
+        value.0 as f64 / 10_f64.powi(SCALE as i32)
+    }
+}
+
+impl<const SCALE: u32> std::ops::Add for Decimal<SCALE> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self::Output {
+        Decimal(self.0 + rhs.0)
+    }
+}
+
+impl<const SCALE: u32> std::ops::AddAssign for Decimal<SCALE> {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl<const SCALE: u32> std::ops::Sub for Decimal<SCALE> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Decimal(self.0 - rhs.0)
+    }
+}
+
+impl<const SCALE: u32> std::ops::Neg for Decimal<SCALE> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self::Output {
+        Decimal(-self.0)
+    }
+}
+
+impl<const SCALE: u32> Zero for Decimal<SCALE> {
+    #[inline(always)]
+    fn zero() -> Self {
+        Self::ZERO
+    }
+
+    #[inline(always)]
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl<const SCALE: u32> TickSized for Decimal<SCALE> {
+    /// Rounds half up to the nearest tick
+    #[inline(always)]
+    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> i64 {
+        let tick_idx = px.0.div_euclid(tick_size.0);
+        let rem = px.0.rem_euclid(tick_size.0);
+
+        tick_idx + (2 * rem >= tick_size.0) as i64
+    }
+
+    #[inline(always)]
+    fn tick_idx_to_px(tick_idx: &i64, tick_size: &Self) -> Self {
+        Decimal(tick_idx * tick_size.0)
+    }
+
+    #[inline(always)]
+    fn round_to_tick_size(val: &Self, tick_size: &Self) -> Self {
+        Self::tick_idx_to_px(&Self::px_to_tick_idx(val, tick_size), tick_size)
+    }
+}
+
+impl<const SCALE: u32> Price for Decimal<SCALE> {
+    #[inline(always)]
+    fn cmp_px(&self, other: &Self) -> std::cmp::Ordering {
+        self.cmp(other)
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        f64::from(*self)
+    }
+
+    #[inline(always)]
+    fn count_better<const REVERSE: bool, A>(levels: &[Level<Self, A>], px: &Self) -> usize {
+        simd::count_i64::<REVERSE, _>(levels, |lvl| lvl.px.0, px.0)
+    }
+}
+
+impl<const SCALE: u32> Amount for Decimal<SCALE> {
+    type Delta = Self;
+
+    #[inline(always)]
+    fn checked_apply_delta(&self, delta: &Self::Delta) -> Option<Self> {
+        self.0
+            .checked_add(delta.0)
+            .filter(|amt| *amt >= 0)
+            .map(Decimal)
+    }
+
+    #[inline(always)]
+    fn as_delta(&self) -> Self::Delta {
+        *self
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        f64::from(*self)
+    }
+}
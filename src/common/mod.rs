@@ -1,15 +1,22 @@
 pub mod communication;
 pub mod intrinsics;
+pub mod simd;
 pub mod types;
 
 mod byte_array_reader;
+mod decimal;
 mod heap_invocable;
 mod object_pool;
+mod seq_lock;
 mod stack_invocable;
+mod tick_schedule;
 mod websocket_listener;
 
 pub use byte_array_reader::ByteArrayReader;
+pub use decimal::{Decimal, ParseDecimalError};
 pub use heap_invocable::HeapInvocable;
 pub use object_pool::ObjectPool;
+pub use seq_lock::SeqLock;
 pub use stack_invocable::StackInvocable;
+pub use tick_schedule::TickSchedule;
 pub use websocket_listener::WebSocketListener;
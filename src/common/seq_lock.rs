@@ -0,0 +1,99 @@
+use std::cell::UnsafeCell;
+use std::hint::spin_loop;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicU64, Ordering};
+
+/// Value shared between threads without locks: readers copy it out and retry if a write
+/// overlapped the copy, writers never wait for readers.
+///
+/// The sequence is odd while a write is in progress. Aligned to a cache line,
+/// so neighbouring data doesn't share it.
+#[repr(C, align(64))]
+pub struct SeqLock<T> {
+    seq: AtomicU64,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T: Copy> SeqLock<T> {
+    pub const fn new(value: T) -> Self {
+        SeqLock {
+            seq: AtomicU64::new(0),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Writes are meant to come from one thread, concurrent writers wait for each other
+    #[inline(always)]
+    pub fn write(&self, value: &T) {
+        let mut seq = self.seq.load(Ordering::Relaxed);
+        loop {
+            if seq & 1 == 1 {
+                spin_loop();
+                seq = self.seq.load(Ordering::Relaxed);
+                continue;
+            }
+
+            match self
+                .seq
+                .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        fence(Ordering::Release);
+
+        unsafe { ptr::write_volatile(self.value.get(), *value) };
+
+        self.seq.store(seq + 2, Ordering::Release);
+    }
+
+    /// `None` if a write is in progress or overlapped the copy
+    #[inline(always)]
+    pub fn try_read(&self) -> Option<T> {
+        let seq = self.seq.load(Ordering::Acquire);
+        if seq & 1 == 1 {
+            return None;
+        }
+
+        // A torn copy may not be a valid `T`, it is only assumed initialized once the sequence matches
+        let value = unsafe { ptr::read_volatile(self.value.get().cast::<MaybeUninit<T>>()) };
+        fence(Ordering::Acquire);
+
+        (self.seq.load(Ordering::Relaxed) == seq).then(|| unsafe { value.assume_init() })
+    }
+
+    /// Spins until a consistent copy is read
+    #[inline(always)]
+    pub fn read(&self) -> T {
+        loop {
+            match self.try_read() {
+                Some(value) => return value,
+                None => spin_loop(),
+            }
+        }
+    }
+
+    /// Number of completed writes times two, e.g. to skip re-reading an unchanged value
+    #[inline(always)]
+    pub fn seq(&self) -> u64 {
+        self.seq.load(Ordering::Acquire)
+    }
+}
+
+impl<T: Copy + Default> Default for SeqLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> std::fmt::Debug for SeqLock<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SeqLock")
+            .field("seq", &self.seq.load(Ordering::Relaxed))
+            .finish()
+    }
+}
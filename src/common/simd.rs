@@ -0,0 +1,182 @@
+//! Explicit SIMD compares, used by `Price::count_better` for the position search of `L2Book`.
+//!
+//! Two lanes per compare: SSE4.2 on x86-64 (enabled in `.cargo/config.toml`, SSE2 is enough for `f64`)
+//! and NEON on aarch64. Other targets fall back to a scalar count.
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+/// Number of `items` whose key is greater (`GREATER`) or less than `pivot`
+#[inline(always)]
+pub fn count_i64<const GREATER: bool, T>(
+    items: &[T],
+    key: impl Fn(&T) -> i64,
+    pivot: i64,
+) -> usize {
+    let pairs = items.chunks_exact(2);
+    let remainder = pairs.remainder();
+
+    let count = count_i64_pairs::<GREATER>(pairs.map(|pair| [key(&pair[0]), key(&pair[1])]), pivot);
+
+    count
+        + remainder
+            .iter()
+            .filter(|item| compare::<GREATER, _>(key(item), pivot))
+            .count()
+}
+
+/// See `count_i64`
+#[inline(always)]
+pub fn count_f64<const GREATER: bool, T>(
+    items: &[T],
+    key: impl Fn(&T) -> f64,
+    pivot: f64,
+) -> usize {
+    let pairs = items.chunks_exact(2);
+    let remainder = pairs.remainder();
+
+    let count = count_f64_pairs::<GREATER>(pairs.map(|pair| [key(&pair[0]), key(&pair[1])]), pivot);
+
+    count
+        + remainder
+            .iter()
+            .filter(|item| compare::<GREATER, _>(key(item), pivot))
+            .count()
+}
+
+#[inline(always)]
+fn compare<const GREATER: bool, V: PartialOrd>(value: V, pivot: V) -> bool {
+    if GREATER {
+        value > pivot
+    } else {
+        value < pivot
+    }
+}
+
+#[cfg(all(target_arch = "x86_64", target_feature = "sse4.2"))]
+#[inline(always)]
+fn count_i64_pairs<const GREATER: bool>(
+    pairs: impl Iterator<Item = [i64; 2]>,
+    pivot: i64,
+) -> usize {
+    unsafe {
+        let pivot = _mm_set1_epi64x(pivot);
+        let mut acc = _mm_setzero_si128();
+
+        for [lo, hi] in pairs {
+            let values = _mm_set_epi64x(hi, lo);
+            let mask = if GREATER {
+                _mm_cmpgt_epi64(values, pivot)
+            } else {
+                _mm_cmpgt_epi64(pivot, values)
+            };
+            // Lanes of the mask are -1 where the compare holds
+            acc = _mm_sub_epi64(acc, mask);
+        }
+
+        let lanes: [u64; 2] = std::mem::transmute(acc);
+        (lanes[0] + lanes[1]) as usize
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn count_f64_pairs<const GREATER: bool>(
+    pairs: impl Iterator<Item = [f64; 2]>,
+    pivot: f64,
+) -> usize {
+    unsafe {
+        let pivot = _mm_set1_pd(pivot);
+        let mut acc = _mm_setzero_si128();
+
+        for [lo, hi] in pairs {
+            let values = _mm_set_pd(hi, lo);
+            let mask = if GREATER {
+                _mm_cmpgt_pd(values, pivot)
+            } else {
+                _mm_cmplt_pd(values, pivot)
+            };
+            acc = _mm_sub_epi64(acc, _mm_castpd_si128(mask));
+        }
+
+        let lanes: [u64; 2] = std::mem::transmute(acc);
+        (lanes[0] + lanes[1]) as usize
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn count_i64_pairs<const GREATER: bool>(
+    pairs: impl Iterator<Item = [i64; 2]>,
+    pivot: i64,
+) -> usize {
+    unsafe {
+        let pivot = vdupq_n_s64(pivot);
+        let mut acc = vdupq_n_u64(0);
+
+        for pair in pairs {
+            let values = vld1q_s64(pair.as_ptr());
+            let mask = if GREATER {
+                vcgtq_s64(values, pivot)
+            } else {
+                vcltq_s64(values, pivot)
+            };
+            acc = vsubq_u64(acc, mask);
+        }
+
+        vaddvq_u64(acc) as usize
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[inline(always)]
+fn count_f64_pairs<const GREATER: bool>(
+    pairs: impl Iterator<Item = [f64; 2]>,
+    pivot: f64,
+) -> usize {
+    unsafe {
+        let pivot = vdupq_n_f64(pivot);
+        let mut acc = vdupq_n_u64(0);
+
+        for pair in pairs {
+            let values = vld1q_f64(pair.as_ptr());
+            let mask = if GREATER {
+                vcgtq_f64(values, pivot)
+            } else {
+                vcltq_f64(values, pivot)
+            };
+            acc = vsubq_u64(acc, mask);
+        }
+
+        vaddvq_u64(acc) as usize
+    }
+}
+
+#[cfg(not(any(
+    target_arch = "aarch64",
+    all(target_arch = "x86_64", target_feature = "sse4.2")
+)))]
+#[inline(always)]
+fn count_i64_pairs<const GREATER: bool>(
+    pairs: impl Iterator<Item = [i64; 2]>,
+    pivot: i64,
+) -> usize {
+    pairs
+        .flatten()
+        .filter(|value| compare::<GREATER, _>(*value, pivot))
+        .count()
+}
+
+#[cfg(not(any(target_arch = "aarch64", target_arch = "x86_64")))]
+#[inline(always)]
+fn count_f64_pairs<const GREATER: bool>(
+    pairs: impl Iterator<Item = [f64; 2]>,
+    pivot: f64,
+) -> usize {
+    pairs
+        .flatten()
+        .filter(|value| compare::<GREATER, _>(*value, pivot))
+        .count()
+}
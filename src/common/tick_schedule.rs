@@ -0,0 +1,126 @@
+use super::types::Price;
+
+const MAX_BANDS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct TickBand<P> {
+    from_px: P,
+    from_tick_idx: i64,
+    tick_size: P,
+    /// Tick index of the band's grid at price zero
+    offset: i64,
+}
+
+/// Tick size by price band, e.g. $0.0001 below $1 and $0.01 above on NASDAQ.
+///
+/// Tick indices are continuous across bands: the first tick of a band follows the last tick of the previous one,
+/// so the index stays monotonic in price and the books can keep indexing levels by ticks.
+#[derive(Debug, Clone, Copy)]
+pub struct TickSchedule<P> {
+    bands: [TickBand<P>; MAX_BANDS],
+    len: usize,
+}
+
+impl<P: Price> TickSchedule<P> {
+    /// Constant tick size
+    pub fn new(tick_size: P) -> Self {
+        let mut bands = [TickBand::default(); MAX_BANDS];
+        bands[0] = TickBand {
+            from_px: P::default(),
+            from_tick_idx: i64::MIN,
+            tick_size,
+            offset: 0,
+        };
+
+        TickSchedule { bands, len: 1 }
+    }
+
+    /// `(from_px, tick_size)` in ascending order of price, the first band also covers everything below it.
+    /// Band boundaries have to lie on the grid of the previous band.
+    pub fn with_bands(bands: &[(P, P)]) -> Self {
+        assert!(
+            !bands.is_empty() && bands.len() <= MAX_BANDS,
+            "Unsupported number of tick bands: len=[{}]",
+            bands.len()
+        );
+
+        let mut schedule = Self::new(bands[0].1);
+        schedule.bands[0].from_px = bands[0].0;
+
+        for (from_px, tick_size) in bands[1..].iter() {
+            let prev = schedule.bands[schedule.len - 1];
+            assert!(
+                *from_px > prev.from_px,
+                "Tick bands are not ascending: px=[{:?}]",
+                from_px
+            );
+
+            let from_tick_idx = P::px_to_tick_idx(from_px, &prev.tick_size) + prev.offset;
+            schedule.bands[schedule.len] = TickBand {
+                from_px: *from_px,
+                from_tick_idx,
+                tick_size: *tick_size,
+                offset: from_tick_idx - P::px_to_tick_idx(from_px, tick_size),
+            };
+            schedule.len += 1;
+        }
+
+        schedule
+    }
+
+    #[inline(always)]
+    pub fn px_to_tick_idx(&self, px: &P) -> i64 {
+        let band = self.band_of_px(px);
+        P::px_to_tick_idx(px, &band.tick_size) + band.offset
+    }
+
+    #[inline(always)]
+    pub fn tick_idx_to_px(&self, tick_idx: &i64) -> P {
+        let band = self.band_of_tick_idx(tick_idx);
+        P::tick_idx_to_px(&(tick_idx - band.offset), &band.tick_size)
+    }
+
+    #[inline(always)]
+    pub fn round_to_tick_size(&self, px: &P) -> P {
+        P::round_to_tick_size(px, &self.band_of_px(px).tick_size)
+    }
+
+    #[inline(always)]
+    pub fn tick_size(&self, px: &P) -> P {
+        self.band_of_px(px).tick_size
+    }
+
+    #[inline(always)]
+    fn band_of_px(&self, px: &P) -> &TickBand<P> {
+        let mut band = &self.bands[0];
+
+        for next in self.bands[1..self.len].iter() {
+            if *px < next.from_px {
+                break;
+            }
+            band = next;
+        }
+
+        band
+    }
+
+    #[inline(always)]
+    fn band_of_tick_idx(&self, tick_idx: &i64) -> &TickBand<P> {
+        let mut band = &self.bands[0];
+
+        for next in self.bands[1..self.len].iter() {
+            if *tick_idx < next.from_tick_idx {
+                break;
+            }
+            band = next;
+        }
+
+        band
+    }
+}
+
+impl<P: Price> From<P> for TickSchedule<P> {
+    fn from(tick_size: P) -> Self {
+        TickSchedule::new(tick_size)
+    }
+}
@@ -1,33 +1,81 @@
+use super::simd;
+
 use num_traits::Zero;
 
+use std::cmp::Ordering;
 use std::fmt::Debug;
 
-#[derive(Default, Debug, Clone, Copy)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    #[default]
+    Bid,
+    Ask,
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct Level<P, A> {
     pub px: P,
     pub amt: A,
+    /// Number of orders at the level, zero if the venue doesn't publish it
+    pub order_count: u32,
+}
+
+impl<P, A> Level<P, A> {
+    /// Level without an order count
+    #[inline(always)]
+    pub fn new(px: P, amt: A) -> Self {
+        Level {
+            px,
+            amt,
+            order_count: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct L2Delta<P, A: Amount> {
     pub px: P,
     pub amt_delta: <A as Amount>::Delta,
+    /// `+1` for a new order at the level, `-1` for a removed one
+    pub order_count_delta: i32,
+}
+
+impl<P, A: Amount> L2Delta<P, A> {
+    /// Delta that doesn't change the order count
+    #[inline(always)]
+    pub fn new(px: P, amt_delta: A::Delta) -> Self {
+        L2Delta {
+            px,
+            amt_delta,
+            order_count_delta: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum L3Delta<P, A> {
+    Add { id: u64, side: Side, px: P, amt: A },
+    Reduce { id: u64, amt: A },
+    Delete { id: u64 },
+    Replace { id: u64, new_id: u64, px: P, amt: A },
 }
 
+/// Tick indices are signed, so prices may be negative or cross zero (e.g. calendar spreads).
 pub trait TickSized {
-    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> usize;
-    fn tick_idx_to_px(tick_idx: &usize, tick_size: &Self) -> Self;
+    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> i64;
+    fn tick_idx_to_px(tick_idx: &i64, tick_size: &Self) -> Self;
     fn round_to_tick_size(val: &Self, tick_size: &Self) -> Self;
 }
 
 impl TickSized for f64 {
     #[inline(always)]
-    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> usize {
-        (px / tick_size).round() as usize
+    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> i64 {
+        (px / tick_size).round() as i64
     }
 
     #[inline(always)]
-    fn tick_idx_to_px(tick_idx: &usize, tick_size: &Self) -> Self {
+    fn tick_idx_to_px(tick_idx: &i64, tick_size: &Self) -> Self {
         *tick_idx as Self * tick_size
     }
 
@@ -38,30 +86,215 @@ impl TickSized for f64 {
     }
 }
 
+/// Price in its tick size units, e.g. exchange-native integer prices
+impl TickSized for i64 {
+    /// Rounds half up to the nearest tick
+    #[inline(always)]
+    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> i64 {
+        px.div_euclid(*tick_size) + (2 * px.rem_euclid(*tick_size) >= *tick_size) as i64
+    }
+
+    #[inline(always)]
+    fn tick_idx_to_px(tick_idx: &i64, tick_size: &Self) -> Self {
+        tick_idx * tick_size
+    }
+
+    #[inline(always)]
+    fn round_to_tick_size(val: &Self, tick_size: &Self) -> Self {
+        Self::px_to_tick_idx(val, tick_size) * tick_size
+    }
+}
+
 pub trait Price:
-    TickSized
-    + Copy
-    + std::ops::Mul<f64, Output = Self>
+    TickSized + Copy + std::cmp::PartialOrd<Self> + Default + PartialEq + Debug
+{
+    /// Total order for sorting, consistent with `PartialOrd` where it is defined (e.g. NaN doesn't panic)
+    fn cmp_px(&self, other: &Self) -> Ordering;
+
+    /// Lossy conversion for analytics, e.g. mid or slippage
+    fn to_f64(&self) -> f64;
+
+    /// Number of `levels` better than `px`: greater for `REVERSE` (bids), less otherwise.
+    /// Levels sorted best first get `px` at this position, see `L2Book`.
+    #[inline(always)]
+    fn count_better<const REVERSE: bool, A>(levels: &[Level<Self, A>], px: &Self) -> usize {
+        levels
+            .iter()
+            .filter(|lvl| if REVERSE { lvl.px > *px } else { lvl.px < *px })
+            .count()
+    }
+}
+
+pub trait Amount:
+    Copy
+    + Zero
+    + std::ops::AddAssign
+    + std::ops::Sub<Output = Self>
     + std::cmp::PartialOrd<Self>
     + Default
-    + PartialEq
     + Debug
 {
+    type Delta: Debug
+        + Copy
+        + PartialEq
+        + std::ops::Neg<Output = Self::Delta>
+        + std::ops::Add<Output = Self::Delta>;
+
+    /// `None` if the resulting amount would be negative or overflow
+    fn checked_apply_delta(&self, delta: &Self::Delta) -> Option<Self>;
+    fn as_delta(&self) -> Self::Delta;
+
+    /// See `Price::to_f64`
+    fn to_f64(&self) -> f64;
+
+    /// Clamps to zero if the delta doesn't fit
+    #[inline(always)]
+    fn apply_delta(&self, delta: &Self::Delta) -> Self {
+        self.checked_apply_delta(delta).unwrap_or_else(Self::zero)
+    }
 }
 
-pub trait Amount: Copy + Zero + std::ops::AddAssign + Default + Debug {
-    type Delta: Debug + Copy;
+/// A delta that doesn't fit the level's (or the order's) amount, e.g. a duplicated or out-of-order message.
+/// The amount is clamped to zero.
+#[derive(Debug, Clone, Copy)]
+pub struct AmountAnomaly<P, A: Amount> {
+    pub side: Side,
+    pub px: P,
+    pub prev_amt: A,
+    pub amt_delta: A::Delta,
+}
 
-    fn apply_delta(&self, delta: &Self::Delta) -> Self;
+impl<P: Debug, A: Amount> AmountAnomaly<P, A> {
+    /// Default reporting of the `_with`-less apply methods
+    pub fn log(&self) {
+        log::warn!(
+            "Amount anomaly, clamped to zero: side=[{:?}], px=[{:?}], prev_amt=[{:?}], amt_delta=[{:?}]",
+            self.side,
+            self.px,
+            self.prev_amt,
+            self.amt_delta
+        );
+    }
 }
 
-impl Price for f64 {}
+impl Price for f64 {
+    #[inline(always)]
+    fn cmp_px(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+
+    #[inline(always)]
+    fn count_better<const REVERSE: bool, A>(levels: &[Level<Self, A>], px: &Self) -> usize {
+        simd::count_f64::<REVERSE, _>(levels, |lvl| lvl.px, *px)
+    }
+}
+
+impl Price for i64 {
+    #[inline(always)]
+    fn cmp_px(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+
+    #[inline(always)]
+    fn count_better<const REVERSE: bool, A>(levels: &[Level<Self, A>], px: &Self) -> usize {
+        simd::count_i64::<REVERSE, _>(levels, |lvl| lvl.px, *px)
+    }
+}
+
+/// Results within this fraction of the operands are float residue, e.g. `0.3 - (0.1 + 0.2)`
+const F64_AMOUNT_EPSILON: f64 = 1e-9;
 
 impl Amount for f64 {
     type Delta = f64;
 
+    /// Residue around zero is snapped to zero rather than reported
     #[inline(always)]
-    fn apply_delta(&self, delta: &Self::Delta) -> Self {
-        self + delta
+    fn checked_apply_delta(&self, delta: &Self::Delta) -> Option<Self> {
+        let amt = self + delta;
+
+        if amt.abs() <= self.abs().max(delta.abs()) * F64_AMOUNT_EPSILON {
+            return Some(0.0);
+        }
+
+        (amt >= 0.0).then_some(amt)
+    }
+
+    #[inline(always)]
+    fn as_delta(&self) -> Self::Delta {
+        *self
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        *self
+    }
+}
+
+impl Amount for i64 {
+    type Delta = i64;
+
+    #[inline(always)]
+    fn checked_apply_delta(&self, delta: &Self::Delta) -> Option<Self> {
+        self.checked_add(*delta).filter(|amt| *amt >= 0)
+    }
+
+    #[inline(always)]
+    fn as_delta(&self) -> Self::Delta {
+        *self
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+}
+
+impl Amount for u64 {
+    type Delta = i64;
+
+    #[inline(always)]
+    fn checked_apply_delta(&self, delta: &Self::Delta) -> Option<Self> {
+        self.checked_add_signed(*delta)
+    }
+
+    #[inline(always)]
+    fn as_delta(&self) -> Self::Delta {
+        *self as i64
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+}
+
+impl Amount for u32 {
+    type Delta = i64;
+
+    #[inline(always)]
+    fn checked_apply_delta(&self, delta: &Self::Delta) -> Option<Self> {
+        (*self as i64)
+            .checked_add(*delta)
+            .and_then(|amt| u32::try_from(amt).ok())
+    }
+
+    #[inline(always)]
+    fn as_delta(&self) -> Self::Delta {
+        *self as i64
+    }
+
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        *self as f64
     }
 }
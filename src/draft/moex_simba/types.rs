@@ -1,5 +1,7 @@
 #![allow(dead_code)]
 
+use crate::common::types::{L3Delta, Side};
+
 /// Generated by ChatGPT from C++
 
 #[repr(C, packed(1))]
@@ -157,8 +159,8 @@ pub struct OrderUpdate {
     pub md_entry_id: i64,
     pub md_entry_px: Decimal5,
     pub md_entry_size: i64,
-    pub md_flags: MDFlagsSet,
-    pub md_flags2: MDFlags2Set,
+    pub md_flags: u64,
+    pub md_flags2: u64,
     pub security_id: i32,
     pub rtp_seq: u32,
     pub md_update_action: MDUpdateAction,
@@ -167,6 +169,38 @@ pub struct OrderUpdate {
 
 impl OrderUpdate {
     pub const TEMPLATE_ID: u16 = 15;
+
+    /// Order-level event in price mantissas, so `OrderStore` maintains the amounts and order counts.
+    /// A change keeps the entry ID, it is applied as a replace with the same ID.
+    pub fn l3_delta(&self) -> Option<L3Delta<i64, i64>> {
+        let (id, px, amt) = (
+            self.md_entry_id as u64,
+            self.md_entry_px.mantissa,
+            self.md_entry_size,
+        );
+        let side = match self.md_entry_type {
+            MDEntryType::Bid => Side::Bid,
+            MDEntryType::Offer => Side::Ask,
+            MDEntryType::EmptyBook => return None,
+        };
+
+        Some(match self.md_update_action {
+            MDUpdateAction::New => L3Delta::Add { id, side, px, amt },
+            MDUpdateAction::Change => L3Delta::Replace {
+                id,
+                new_id: id,
+                px,
+                amt,
+            },
+            MDUpdateAction::Delete => L3Delta::Delete { id },
+        })
+    }
+
+    /// Last update of a matching event, see `ConflatedBook::commit`
+    #[inline(always)]
+    pub fn is_end_of_transaction(&self) -> bool {
+        self.md_flags & MDFlagsSet::EndOfTransaction as u64 != 0
+    }
 }
 
 #[repr(C, packed(1))]
@@ -178,8 +212,8 @@ pub struct OrderExecution {
     pub last_px: Decimal5,
     pub last_qty: i64,
     pub trade_id: i64,
-    pub md_flags: MDFlagsSet,
-    pub md_flags2: MDFlags2Set,
+    pub md_flags: u64,
+    pub md_flags2: u64,
     pub security_id: i32,
     pub rtp_seq: u32,
     pub md_update_action: MDUpdateAction,
@@ -188,6 +222,12 @@ pub struct OrderExecution {
 
 impl OrderExecution {
     pub const TEMPLATE_ID: u16 = 16;
+
+    /// See `OrderUpdate::is_end_of_transaction`
+    #[inline(always)]
+    pub fn is_end_of_transaction(&self) -> bool {
+        self.md_flags & MDFlagsSet::EndOfTransaction as u64 != 0
+    }
 }
 
 #[repr(C, packed(1))]
@@ -223,8 +263,8 @@ pub struct OrderBookSnapshotEntry {
     pub md_entry_px: Decimal5NULL,
     pub md_entry_size: i64,
     pub trade_id: i64,
-    pub md_flags: MDFlagsSet,
-    pub md_flags2: MDFlags2Set,
+    pub md_flags: u64,
+    pub md_flags2: u64,
     pub md_entry_type: MDEntryType,
 }
 
@@ -1,4 +1,8 @@
-use crate::common::intrinsics::*;
+use super::Price4Wrapper;
+use crate::common::{
+    intrinsics::*,
+    types::{self, AmountAnomaly},
+};
 
 use itchy::{Body, Message, Price4, Side};
 
@@ -14,9 +18,9 @@ struct OrderPool {
 }
 
 impl OrderPool {
-    pub fn new() -> Self {
+    pub fn new(reserve_size: usize) -> Self {
         OrderPool {
-            orders: Vec::with_capacity(2_usize.pow(30)),
+            orders: Vec::with_capacity(reserve_size),
         }
     }
 
@@ -29,38 +33,79 @@ impl OrderPool {
     pub fn get_mut(&mut self, reference: &u64) -> &mut Option<Order> {
         let reference = *reference as usize;
 
-        if unlikely(self.orders.len() <= reference as usize) {
+        if unlikely(self.orders.len() <= reference) {
             self.orders.resize(reference + 1, None);
             log::debug!("Resize triggered: new_len=[{}]", self.orders.len());
         }
 
         &mut self.orders[reference]
     }
+}
 
-    #[inline(always)]
-    pub fn get(&self, reference: &u64) -> &Option<Order> {
-        let reference = *reference as usize;
-        &self.orders[reference]
+/// Reduces the order by at most its remaining shares, an overfill (e.g. a duplicated message) is reported.
+/// Returns the reduced shares and the order count delta, a fully executed order leaves the level.
+#[inline(always)]
+fn reduce_shares(
+    order: &mut Order,
+    shares: u32,
+    on_anomaly: &mut impl FnMut(&AmountAnomaly<Price4Wrapper, u32>),
+) -> (u32, i32) {
+    if unlikely(shares > order.shares) {
+        on_anomaly(&AmountAnomaly {
+            side: match order.side {
+                Side::Buy => types::Side::Bid,
+                Side::Sell => types::Side::Ask,
+            },
+            px: Price4Wrapper(order.price),
+            prev_amt: order.shares,
+            amt_delta: -(shares as i64),
+        });
     }
+
+    let reduced = shares.min(order.shares);
+    order.shares -= reduced;
+    (reduced, -((reduced > 0 && order.shares == 0) as i32))
 }
 
 pub struct ItchIntoL2Deltas {
     orders: OrderPool,
 }
 
+impl Default for ItchIntoL2Deltas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ItchIntoL2Deltas {
     pub fn new() -> Self {
+        Self::with_capacity(2_usize.pow(30))
+    }
+
+    pub fn with_capacity(reserve_size: usize) -> Self {
         ItchIntoL2Deltas {
-            orders: OrderPool::new(),
+            orders: OrderPool::new(reserve_size),
         }
     }
 
-    #[allow(unused_variables)]
+    /// Reports `(side, price, amount delta, order count delta)` per touched level, overfills are logged
     #[inline(always)]
     pub fn apply_message(
         &mut self,
         msg: &Message,
-        mut process_l2_delta: impl FnMut(&Side, &Price4, &i64),
+        process_l2_delta: impl FnMut(&Side, &Price4, &i64, &i32),
+    ) {
+        self.apply_message_with(msg, process_l2_delta, AmountAnomaly::log);
+    }
+
+    /// Executions and cancels larger than the order are clamped and reported through `on_anomaly`
+    #[allow(unused_variables)]
+    #[inline(always)]
+    pub fn apply_message_with(
+        &mut self,
+        msg: &Message,
+        mut process_l2_delta: impl FnMut(&Side, &Price4, &i64, &i32),
+        mut on_anomaly: impl FnMut(&AmountAnomaly<Price4Wrapper, u32>),
     ) {
         match &msg.body {
             Body::AddOrder(add_order) => {
@@ -77,6 +122,7 @@ impl ItchIntoL2Deltas {
                     &add_order.side,
                     &add_order.price,
                     &(add_order.shares as i64),
+                    &1,
                 );
             }
             Body::OrderExecuted {
@@ -89,9 +135,14 @@ impl ItchIntoL2Deltas {
                     None => return,
                 };
 
-                order.shares -= executed;
+                let (reduced, order_count_delta) = reduce_shares(order, *executed, &mut on_anomaly);
 
-                process_l2_delta(&order.side, &order.price, &(-1 * *executed as i64));
+                process_l2_delta(
+                    &order.side,
+                    &order.price,
+                    &(-1 * reduced as i64),
+                    &order_count_delta,
+                );
             }
             Body::OrderExecutedWithPrice {
                 reference,
@@ -105,9 +156,14 @@ impl ItchIntoL2Deltas {
                     None => return,
                 };
 
-                order.shares -= executed;
+                let (reduced, order_count_delta) = reduce_shares(order, *executed, &mut on_anomaly);
 
-                process_l2_delta(&order.side, &order.price, &(-1 * *executed as i64));
+                process_l2_delta(
+                    &order.side,
+                    &order.price,
+                    &(-1 * reduced as i64),
+                    &order_count_delta,
+                );
             }
             Body::OrderCancelled {
                 reference,
@@ -118,21 +174,32 @@ impl ItchIntoL2Deltas {
                     None => return,
                 };
 
-                order.shares -= cancelled;
+                let (reduced, order_count_delta) =
+                    reduce_shares(order, *cancelled, &mut on_anomaly);
 
-                process_l2_delta(&order.side, &order.price, &(-1 * *cancelled as i64));
+                process_l2_delta(
+                    &order.side,
+                    &order.price,
+                    &(-1 * reduced as i64),
+                    &order_count_delta,
+                );
             }
             Body::DeleteOrder { reference } => {
-                let order = match self.orders.get(reference) {
+                let order = match self.orders.get_mut(reference).take() {
                     Some(o) => o,
                     None => return,
                 };
 
-                process_l2_delta(&order.side, &order.price, &(-1 * order.shares as i64));
+                process_l2_delta(
+                    &order.side,
+                    &order.price,
+                    &(-1 * order.shares as i64),
+                    &-((order.shares > 0) as i32),
+                );
             }
             Body::ReplaceOrder(replace_order) => {
-                let old_order = match self.orders.get(&replace_order.old_reference) {
-                    Some(o) => *o,
+                let old_order = match self.orders.get_mut(&replace_order.old_reference).take() {
+                    Some(o) => o,
                     None => return,
                 };
 
@@ -149,12 +216,14 @@ impl ItchIntoL2Deltas {
                     &old_order.side,
                     &old_order.price,
                     &(-1 * old_order.shares as i64),
+                    &-((old_order.shares > 0) as i32),
                 );
 
                 process_l2_delta(
                     &old_order.side,
                     &replace_order.price,
                     &(replace_order.shares as i64),
+                    &1,
                 );
             }
             _ => (),
@@ -0,0 +1,64 @@
+use super::Price4Wrapper;
+use crate::common::types::{L3Delta, Side};
+
+use itchy::{Body, Message};
+
+/// Stateless mapping of ITCH order messages into `L3Delta` events,
+/// the orders themselves are kept by `OrderStore`.
+#[derive(Default)]
+pub struct ItchIntoL3Deltas {}
+
+impl ItchIntoL3Deltas {
+    pub fn new() -> Self {
+        ItchIntoL3Deltas {}
+    }
+
+    #[inline(always)]
+    pub fn apply_message(
+        &self,
+        msg: &Message,
+        mut process_l3_delta: impl FnMut(&L3Delta<Price4Wrapper, u32>),
+    ) {
+        match &msg.body {
+            Body::AddOrder(add_order) => process_l3_delta(&L3Delta::Add {
+                id: add_order.reference,
+                side: match add_order.side {
+                    itchy::Side::Buy => Side::Bid,
+                    itchy::Side::Sell => Side::Ask,
+                },
+                px: Price4Wrapper(add_order.price),
+                amt: add_order.shares,
+            }),
+            Body::OrderExecuted {
+                reference,
+                executed,
+                ..
+            }
+            | Body::OrderExecutedWithPrice {
+                reference,
+                executed,
+                ..
+            } => process_l3_delta(&L3Delta::Reduce {
+                id: *reference,
+                amt: *executed,
+            }),
+            Body::OrderCancelled {
+                reference,
+                cancelled,
+            } => process_l3_delta(&L3Delta::Reduce {
+                id: *reference,
+                amt: *cancelled,
+            }),
+            Body::DeleteOrder { reference } => {
+                process_l3_delta(&L3Delta::Delete { id: *reference })
+            }
+            Body::ReplaceOrder(replace_order) => process_l3_delta(&L3Delta::Replace {
+                id: replace_order.old_reference,
+                new_id: replace_order.new_reference,
+                px: Price4Wrapper(replace_order.price),
+                amt: replace_order.shares,
+            }),
+            _ => (),
+        }
+    }
+}
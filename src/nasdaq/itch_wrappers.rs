@@ -1,4 +1,7 @@
-use crate::common::types::{Amount, Price, TickSized};
+use crate::common::simd;
+use crate::common::types::{Level, Price, TickSized};
+use crate::common::TickSchedule;
+use crate::order_book::SharedValue;
 
 use itchy::Price4;
 
@@ -19,23 +22,14 @@ impl PartialOrd for Price4Wrapper {
     }
 }
 
-impl std::ops::Mul<f64> for Price4Wrapper {
-    type Output = Self;
-
-    #[inline(always)]
-    fn mul(self, rhs: f64) -> Self::Output {
-        Price4Wrapper(Price4::from((self.0.raw() as f64 * rhs) as u32))
-    }
-}
-
 impl TickSized for Price4Wrapper {
     #[inline(always)]
-    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> usize {
-        (px.0.raw() / tick_size.0.raw()) as usize
+    fn px_to_tick_idx(px: &Self, tick_size: &Self) -> i64 {
+        (px.0.raw() / tick_size.0.raw()) as i64
     }
 
     #[inline(always)]
-    fn tick_idx_to_px(tick_idx: &usize, tick_size: &Self) -> Self {
+    fn tick_idx_to_px(tick_idx: &i64, tick_size: &Self) -> Self {
         Price4Wrapper(Price4::from(*tick_idx as u32 * tick_size.0.raw()))
     }
 
@@ -53,13 +47,40 @@ impl Default for Price4Wrapper {
     }
 }
 
-impl Price for Price4Wrapper {}
+impl Price for Price4Wrapper {
+    #[inline(always)]
+    fn cmp_px(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.raw().cmp(&other.0.raw())
+    }
 
-impl Amount for u32 {
-    type Delta = i64;
+    #[inline(always)]
+    fn to_f64(&self) -> f64 {
+        f64::from(*self)
+    }
 
     #[inline(always)]
-    fn apply_delta(&self, delta: &i64) -> Self {
-        (*self as i64 + delta) as u32
+    fn count_better<const REVERSE: bool, A>(levels: &[Level<Self, A>], px: &Self) -> usize {
+        simd::count_i64::<REVERSE, _>(levels, |lvl| lvl.px.0.raw() as i64, px.0.raw() as i64)
+    }
+}
+
+impl SharedValue for Price4Wrapper {
+    const KIND: u32 = 6;
+    const SCALE: u32 = 4;
+}
+
+impl Price4Wrapper {
+    /// Rule 612 minimum increments: $0.0001 below $1, $0.01 from $1
+    pub fn tick_schedule() -> TickSchedule<Self> {
+        TickSchedule::with_bands(&[
+            (
+                Price4Wrapper(Price4::from(0)),
+                Price4Wrapper(Price4::from(1)),
+            ),
+            (
+                Price4Wrapper(Price4::from(10_000)),
+                Price4Wrapper(Price4::from(100)),
+            ),
+        ])
     }
 }
@@ -1,5 +1,7 @@
 mod itch_into_l2_deltas;
+mod itch_into_l3_deltas;
 mod itch_wrappers;
 
 pub use itch_into_l2_deltas::ItchIntoL2Deltas;
+pub use itch_into_l3_deltas::ItchIntoL3Deltas;
 pub use itch_wrappers::Price4Wrapper;
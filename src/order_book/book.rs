@@ -0,0 +1,302 @@
+use super::{BookChecksum, L2BookBuilder, L2DeltaAggregator, OrderStore, TopOfBook, DYNAMIC_DEPTH};
+use super::{DenseLevels, LevelObserver, LevelStorage, TopLevels, VecLevels};
+use crate::common::types::{Amount, AmountAnomaly, L2Delta, L3Delta, Level, Price, Side};
+use crate::common::{SeqLock, TickSchedule};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookState {
+    Empty,
+    OneSided,
+    Normal,
+    Locked,
+    Crossed,
+}
+
+/// Both sides of an instrument's book.
+///
+/// Locked/crossed books are reported through `state` rather than rejected:
+/// they happen transiently on many feeds and it is up to the strategy how to react.
+///
+/// `S`, `T` and `O` are the same for both sides, see `L2BookBuilder`.
+#[derive(Debug, Clone)]
+pub struct OrderBook<
+    P: Price,
+    A: Amount,
+    const N: usize,
+    S: LevelStorage<A> = DenseLevels<A>,
+    T: TopLevels<P, A, N> = VecLevels<P, A>,
+    O: LevelObserver<P, A> = (),
+> {
+    bid: L2BookBuilder<P, A, N, true, S, T, O>,
+    ask: L2BookBuilder<P, A, N, false, S, T, O>,
+    tick_schedule: TickSchedule<P>,
+}
+
+impl<
+        P: Price,
+        A: Amount,
+        const N: usize,
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, N>,
+        O: LevelObserver<P, A>,
+    > OrderBook<P, A, N, S, T, O>
+{
+    /// Not available for `N = DYNAMIC_DEPTH`, see `with_depth`
+    pub fn new(
+        start_px: P,
+        end_px: Option<P>,
+        tick_schedule: impl Into<TickSchedule<P>>,
+        max_ticks: Option<usize>,
+    ) -> Self
+    where
+        O: Default,
+    {
+        const { assert!(N != DYNAMIC_DEPTH, "Use with_depth for DYNAMIC_DEPTH") };
+        Self::with_depth(start_px, end_px, tick_schedule, max_ticks, N)
+    }
+
+    /// For `N = DYNAMIC_DEPTH` the depth is chosen at runtime, see `L2BookBuilder::with_depth`
+    pub fn with_depth(
+        start_px: P,
+        end_px: Option<P>,
+        tick_schedule: impl Into<TickSchedule<P>>,
+        max_ticks: Option<usize>,
+        depth: usize,
+    ) -> Self
+    where
+        O: Default,
+    {
+        let tick_schedule = tick_schedule.into();
+
+        OrderBook {
+            bid: L2BookBuilder::with_depth(start_px, end_px, tick_schedule, max_ticks, depth),
+            ask: L2BookBuilder::with_depth(start_px, end_px, tick_schedule, max_ticks, depth),
+            tick_schedule,
+        }
+    }
+
+    /// See `L2BookBuilder::with_observer`
+    pub fn with_observers<U: LevelObserver<P, A>>(
+        self,
+        bid: U,
+        ask: U,
+    ) -> OrderBook<P, A, N, S, T, U> {
+        OrderBook {
+            bid: self.bid.with_observer(bid),
+            ask: self.ask.with_observer(ask),
+            tick_schedule: self.tick_schedule,
+        }
+    }
+
+    #[inline(always)]
+    pub fn apply_l2_snapshot(&mut self, bids: &[Level<P, A>], asks: &[Level<P, A>]) {
+        self.bid.apply_l2_snapshot(bids);
+        self.ask.apply_l2_snapshot(asks);
+    }
+
+    #[inline(always)]
+    pub fn apply_l2_upserts(&mut self, bids: &[Level<P, A>], asks: &[Level<P, A>]) {
+        self.bid.apply_l2_upserts(bids);
+        self.ask.apply_l2_upserts(asks);
+    }
+
+    #[inline(always)]
+    pub fn apply_l2_deltas(&mut self, bids: &[L2Delta<P, A>], asks: &[L2Delta<P, A>]) {
+        self.bid.apply_l2_deltas(bids);
+        self.ask.apply_l2_deltas(asks);
+    }
+
+    #[inline(always)]
+    pub fn apply_l2_deltas_with(
+        &mut self,
+        bids: &[L2Delta<P, A>],
+        asks: &[L2Delta<P, A>],
+        mut on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        self.bid.apply_l2_deltas_with(bids, &mut on_anomaly);
+        self.ask.apply_l2_deltas_with(asks, &mut on_anomaly);
+    }
+
+    /// Orders of both sides are kept in `orders`, see `apply_l3_deltas_with`
+    #[inline(always)]
+    pub fn apply_l3_deltas(&mut self, orders: &mut OrderStore<P, A>, l3_deltas: &[L3Delta<P, A>]) {
+        self.apply_l3_deltas_with(orders, l3_deltas, AmountAnomaly::log);
+    }
+
+    /// Every event is resolved against the store once, in order, and its `L2Delta`s go to the side
+    /// they belong to, e.g. an add reusing the ID of an order on the other side updates both.
+    #[inline(always)]
+    pub fn apply_l3_deltas_with(
+        &mut self,
+        orders: &mut OrderStore<P, A>,
+        l3_deltas: &[L3Delta<P, A>],
+        mut on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        self.bid.begin_top_changes();
+        self.ask.begin_top_changes();
+
+        for l3_delta in l3_deltas.iter() {
+            let mut overfill = None;
+
+            orders.apply_with(
+                l3_delta,
+                |side, l2_delta| match side {
+                    Side::Bid => self.bid.apply_l2_delta(l2_delta, &mut on_anomaly),
+                    Side::Ask => self.ask.apply_l2_delta(l2_delta, &mut on_anomaly),
+                },
+                |anomaly| overfill = Some(*anomaly),
+            );
+
+            if let Some(anomaly) = overfill {
+                on_anomaly(&anomaly);
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn flush_l2_deltas(&mut self, l2_deltas: &mut L2DeltaAggregator<P, A>) {
+        l2_deltas.flush(&mut self.bid, &mut self.ask);
+    }
+
+    #[inline(always)]
+    pub fn bid(&self) -> &L2BookBuilder<P, A, N, true, S, T, O> {
+        &self.bid
+    }
+
+    #[inline(always)]
+    pub fn ask(&self) -> &L2BookBuilder<P, A, N, false, S, T, O> {
+        &self.ask
+    }
+
+    #[inline(always)]
+    pub fn best_bid(&self) -> Option<Level<P, A>> {
+        self.bid.book().levels().first().copied()
+    }
+
+    #[inline(always)]
+    pub fn best_ask(&self) -> Option<Level<P, A>> {
+        self.ask.book().levels().first().copied()
+    }
+
+    /// Best `D` levels of both sides
+    #[inline(always)]
+    pub fn top_of_book<const D: usize>(&self) -> TopOfBook<P, A, D> {
+        TopOfBook::new(self.bid.book().levels(), self.ask.book().levels())
+    }
+
+    /// Makes the top available to other threads without locks, see `SeqLock`.
+    /// Meant to be called by the thread applying the updates, after each of them.
+    #[inline(always)]
+    pub fn publish_top<const D: usize>(&self, top: &SeqLock<TopOfBook<P, A, D>>) {
+        top.write(&self.top_of_book());
+    }
+
+    /// See `BookChecksum`, levels beyond the depth of the book are included
+    #[inline(always)]
+    pub fn checksum(&self, checksum: &mut impl BookChecksum<P, A>) -> u32 {
+        checksum.checksum(self.bid.levels(), self.ask.levels())
+    }
+
+    /// Compares the checksum published by the venue with ours, on a mismatch `on_mismatch` gets
+    /// the computed one, e.g. to drop the book and request a snapshot.
+    #[inline(always)]
+    pub fn verify_checksum(
+        &self,
+        checksum: &mut impl BookChecksum<P, A>,
+        expected: u32,
+        on_mismatch: impl FnOnce(u32),
+    ) -> bool {
+        let computed = self.checksum(checksum);
+        if computed != expected {
+            on_mismatch(computed);
+        }
+
+        computed == expected
+    }
+
+    #[inline(always)]
+    pub fn state(&self) -> BookState {
+        match (self.best_bid(), self.best_ask()) {
+            (None, None) => BookState::Empty,
+            (Some(_), None) | (None, Some(_)) => BookState::OneSided,
+            (Some(bid), Some(ask)) => {
+                if bid.px < ask.px {
+                    BookState::Normal
+                } else if bid.px == ask.px {
+                    BookState::Locked
+                } else {
+                    BookState::Crossed
+                }
+            }
+        }
+    }
+
+    /// Spread in ticks, negative when the book is crossed.
+    #[inline(always)]
+    pub fn spread_ticks(&self) -> Option<i64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+
+        Some(
+            self.tick_schedule.px_to_tick_idx(&ask.px) - self.tick_schedule.px_to_tick_idx(&bid.px),
+        )
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.bid.apply_l2_snapshot(&[]);
+        self.ask.apply_l2_snapshot(&[]);
+    }
+}
+
+impl<
+        P: Price,
+        A: Amount,
+        const N: usize,
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, N>,
+        O: LevelObserver<P, A>,
+    > OrderBook<P, A, N, S, T, O>
+{
+    #[inline(always)]
+    pub fn spread(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+
+        Some(ask.px.to_f64() - bid.px.to_f64())
+    }
+
+    #[inline(always)]
+    pub fn mid(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+
+        Some((bid.px.to_f64() + ask.px.to_f64()) / 2.0)
+    }
+
+    /// Mid of the average fill prices of `amt` on both sides, `None` if a side can't fill it completely
+    pub fn depth_weighted_mid(&self, amt: A) -> Option<f64> {
+        let bid = self.bid.cost_to_fill(amt)?;
+        let ask = self.ask.cost_to_fill(amt)?;
+
+        if bid.filled_amt < amt || ask.filled_amt < amt {
+            return None;
+        }
+
+        Some((bid.avg_px + ask.avg_px) / 2.0)
+    }
+
+    /// Mid weighted by the opposite side amounts: the price leans towards the thinner side.
+    #[inline(always)]
+    pub fn microprice(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+
+        let (bid_amt, ask_amt) = (bid.amt.to_f64(), ask.amt.to_f64());
+        if bid_amt + ask_amt == 0.0 {
+            return None;
+        }
+
+        Some((bid.px.to_f64() * ask_amt + ask.px.to_f64() * bid_amt) / (bid_amt + ask_amt))
+    }
+}
@@ -0,0 +1,174 @@
+use crate::common::types::Level;
+use crate::common::Decimal;
+
+use std::fmt::Write;
+
+/// Prices and amounts as venues print them in checksum strings: with a fixed number of decimals.
+pub trait ChecksumValue {
+    fn write_fixed(&self, decimals: u32, out: &mut String);
+}
+
+impl ChecksumValue for f64 {
+    #[inline(always)]
+    fn write_fixed(&self, decimals: u32, out: &mut String) {
+        write!(out, "{:.*}", decimals as usize, self).unwrap();
+    }
+}
+
+/// Exact, digits beyond `decimals` are rounded half away from zero
+impl<const SCALE: u32> ChecksumValue for Decimal<SCALE> {
+    #[inline(always)]
+    fn write_fixed(&self, decimals: u32, out: &mut String) {
+        let mut abs = self.mantissa().unsigned_abs() as u128;
+        let mut scale = SCALE;
+        if decimals < SCALE {
+            let div = 10_u128.pow(SCALE - decimals);
+            abs = (abs + div / 2) / div;
+            scale = decimals;
+        }
+
+        if self.mantissa() < 0 && abs != 0 {
+            out.push('-');
+        }
+        let unit = 10_u128.pow(scale);
+        write!(out, "{}", abs / unit).unwrap();
+        if decimals > 0 {
+            write!(out, ".{:0width$}", abs % unit, width = scale as usize).unwrap();
+            out.extend(std::iter::repeat_n('0', (decimals - scale) as usize));
+        }
+    }
+}
+
+/// Venue-specific CRC32 of the top levels, see `OrderBook::verify_checksum`.
+/// Bids and asks come best first.
+pub trait BookChecksum<P, A> {
+    fn checksum(
+        &mut self,
+        bids: impl Iterator<Item = Level<P, A>>,
+        asks: impl Iterator<Item = Level<P, A>>,
+    ) -> u32;
+}
+
+/// Kraken: the top `depth` asks then bids, each price and amount without the decimal point
+/// and leading zeros, concatenated.
+#[derive(Debug, Clone)]
+pub struct KrakenChecksum {
+    depth: usize,
+    px_decimals: u32,
+    amt_decimals: u32,
+    buf: String,
+    value_buf: String,
+}
+
+impl KrakenChecksum {
+    /// Decimals as published for the pair, the depth is 10 for the public feed
+    pub fn new(depth: usize, px_decimals: u32, amt_decimals: u32) -> Self {
+        KrakenChecksum {
+            depth,
+            px_decimals,
+            amt_decimals,
+            buf: String::new(),
+            value_buf: String::new(),
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, value: &impl ChecksumValue, decimals: u32) {
+        self.value_buf.clear();
+        value.write_fixed(decimals, &mut self.value_buf);
+
+        self.buf.extend(
+            self.value_buf
+                .chars()
+                .filter(|c| *c != '.')
+                .skip_while(|c| *c == '0'),
+        );
+    }
+}
+
+impl<P: ChecksumValue, A: ChecksumValue> BookChecksum<P, A> for KrakenChecksum {
+    fn checksum(
+        &mut self,
+        bids: impl Iterator<Item = Level<P, A>>,
+        asks: impl Iterator<Item = Level<P, A>>,
+    ) -> u32 {
+        self.buf.clear();
+
+        for lvl in asks.take(self.depth).chain(bids.take(self.depth)) {
+            self.push(&lvl.px, self.px_decimals);
+            self.push(&lvl.amt, self.amt_decimals);
+        }
+
+        crc32fast::hash(self.buf.as_bytes())
+    }
+}
+
+/// OKX: `bid_px:bid_amt:ask_px:ask_amt:...` over the top `depth` levels, a side running out is skipped.
+/// Values are printed as OKX sends them, without trailing zeros, e.g. `3366` for `3366.0`.
+/// OKX publishes the checksum as a signed integer, compare with `as u32`.
+#[derive(Debug, Clone)]
+pub struct OkxChecksum {
+    depth: usize,
+    px_decimals: u32,
+    amt_decimals: u32,
+    buf: String,
+}
+
+impl OkxChecksum {
+    /// Decimals as published for the instrument, the depth is 25 for the public feed
+    pub fn new(depth: usize, px_decimals: u32, amt_decimals: u32) -> Self {
+        OkxChecksum {
+            depth,
+            px_decimals,
+            amt_decimals,
+            buf: String::new(),
+        }
+    }
+
+    #[inline(always)]
+    fn push<P: ChecksumValue, A: ChecksumValue>(&mut self, lvl: &Level<P, A>) {
+        if !self.buf.is_empty() {
+            self.buf.push(':');
+        }
+        Self::push_value(&mut self.buf, &lvl.px, self.px_decimals);
+        self.buf.push(':');
+        Self::push_value(&mut self.buf, &lvl.amt, self.amt_decimals);
+    }
+
+    #[inline(always)]
+    fn push_value(buf: &mut String, value: &impl ChecksumValue, decimals: u32) {
+        value.write_fixed(decimals, buf);
+        if decimals > 0 {
+            let len = buf.trim_end_matches('0').trim_end_matches('.').len();
+            buf.truncate(len);
+        }
+    }
+}
+
+impl<P: ChecksumValue, A: ChecksumValue> BookChecksum<P, A> for OkxChecksum {
+    fn checksum(
+        &mut self,
+        bids: impl Iterator<Item = Level<P, A>>,
+        asks: impl Iterator<Item = Level<P, A>>,
+    ) -> u32 {
+        self.buf.clear();
+
+        let mut bids = bids.take(self.depth);
+        let mut asks = asks.take(self.depth);
+        loop {
+            match (bids.next(), asks.next()) {
+                (None, None) => break,
+                (bid, ask) => {
+                    if let Some(bid) = bid {
+                        self.push(&bid);
+                    }
+                    if let Some(ask) = ask {
+                        self.push(&ask);
+                    }
+                }
+            }
+        }
+
+        crc32fast::hash(self.buf.as_bytes())
+    }
+}
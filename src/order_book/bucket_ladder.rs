@@ -0,0 +1,136 @@
+use super::{LevelObserver, PriceLevel};
+use crate::common::types::{Amount, Price};
+use crate::common::TickSchedule;
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone)]
+pub enum BucketGrid<P> {
+    /// Every `n` ticks of the tick schedule
+    Ticks(u64),
+    /// Geometric buckets `bps` basis points wide, for positive prices
+    Bps(f64),
+    /// Bucket boundaries in ascending order, bucket `i` is `[grid[i], grid[i + 1])`,
+    /// prices below the first boundary fall into bucket `-1`
+    Prices(Vec<P>),
+}
+
+/// Coarse ladder of one side: amounts and order counts summed per bucket of prices.
+///
+/// The grid is fixed, so the ladder is maintained from level changes (see `LevelObserver`)
+/// without walking the book, attach it with `L2BookBuilder::with_observer`.
+#[derive(Debug, Clone)]
+pub struct BucketLadder<P, A> {
+    grid: BucketGrid<P>,
+    tick_schedule: TickSchedule<P>,
+    buckets: BTreeMap<i64, Bucket<A>>,
+}
+
+/// A bucket is removed once its last level is, float amounts may not sum back to zero
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket<A> {
+    level: PriceLevel<A>,
+    level_count: u32,
+}
+
+impl<P: Price, A: Amount> BucketLadder<P, A> {
+    pub fn new(grid: BucketGrid<P>, tick_schedule: impl Into<TickSchedule<P>>) -> Self {
+        match &grid {
+            BucketGrid::Ticks(ticks) => assert!(*ticks > 0, "Empty tick buckets"),
+            BucketGrid::Bps(bps) => assert!(*bps > 0.0, "Empty bps buckets: bps=[{}]", bps),
+            BucketGrid::Prices(grid) => assert!(
+                grid.windows(2).all(|pair| pair[0] < pair[1]),
+                "Bucket boundaries are not ascending"
+            ),
+        }
+
+        BucketLadder {
+            grid,
+            tick_schedule: tick_schedule.into(),
+            buckets: BTreeMap::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn bucket_of(&self, px: &P) -> i64 {
+        match &self.grid {
+            BucketGrid::Ticks(ticks) => self
+                .tick_schedule
+                .px_to_tick_idx(px)
+                .div_euclid(*ticks as i64),
+            BucketGrid::Bps(bps) => (px.to_f64().ln() / (1.0 + bps / 1e4).ln()).floor() as i64,
+            BucketGrid::Prices(grid) => grid.partition_point(|from_px| from_px <= px) as i64 - 1,
+        }
+    }
+
+    /// Lower bound of the bucket
+    pub fn bucket_px(&self, bucket: i64) -> f64 {
+        match &self.grid {
+            BucketGrid::Ticks(ticks) => self
+                .tick_schedule
+                .tick_idx_to_px(&(bucket * *ticks as i64))
+                .to_f64(),
+            BucketGrid::Bps(bps) => (1.0 + bps / 1e4).powi(bucket as i32),
+            BucketGrid::Prices(grid) => match usize::try_from(bucket) {
+                Ok(idx) => grid[idx].to_f64(),
+                Err(_) => f64::NEG_INFINITY,
+            },
+        }
+    }
+
+    #[inline(always)]
+    pub fn get(&self, bucket: i64) -> PriceLevel<A> {
+        self.buckets
+            .get(&bucket)
+            .map_or_else(PriceLevel::default, |bucket| bucket.level)
+    }
+
+    /// Non-empty buckets in ascending order of price
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (i64, PriceLevel<A>)> + '_ {
+        self.buckets
+            .iter()
+            .map(|(bucket_idx, bucket)| (*bucket_idx, bucket.level))
+    }
+
+    /// Number of non-empty buckets
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+impl<P: Price, A: Amount> LevelObserver<P, A> for BucketLadder<P, A> {
+    #[inline(always)]
+    fn on_level_change(&mut self, px: P, prev: &PriceLevel<A>, level: &PriceLevel<A>) {
+        let (was_occupied, is_occupied) = (!prev.amt.is_zero(), !level.amt.is_zero());
+        if !was_occupied && !is_occupied {
+            return;
+        }
+
+        let bucket_idx = self.bucket_of(&px);
+        let bucket = self.buckets.entry(bucket_idx).or_default();
+
+        bucket.level.amt = bucket
+            .level
+            .amt
+            .apply_delta(&(level.amt.as_delta() + -prev.amt.as_delta()));
+        bucket.level.order_count =
+            (bucket.level.order_count + level.order_count).saturating_sub(prev.order_count);
+        bucket.level_count =
+            (bucket.level_count + is_occupied as u32).saturating_sub(was_occupied as u32);
+
+        if bucket.level_count == 0 {
+            self.buckets.remove(&bucket_idx);
+        }
+    }
+
+    #[inline(always)]
+    fn on_clear(&mut self) {
+        self.buckets.clear();
+    }
+}
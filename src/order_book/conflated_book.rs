@@ -0,0 +1,104 @@
+use super::{DenseLevels, LevelObserver, LevelStorage, TopLevels, VecLevels};
+use super::{L2DeltaAggregator, OrderBook, OrderStore};
+use crate::common::types::{Amount, L2Delta, L3Delta, Level, Price, Side};
+
+/// Applies the updates of one matching event at once, so the book is never observed
+/// half-way through a sweep, e.g. transiently crossed.
+///
+/// Updates are buffered and netted (see `L2DeltaAggregator`) until a transaction boundary:
+/// an explicit `commit`, e.g. on the MOEX `EndOfTransaction` flag or at the end of a packet,
+/// or a new timestamp passed to `start_at`, e.g. for ITCH messages of one sweep.
+#[derive(Debug, Clone)]
+pub struct ConflatedBook<
+    P: Price,
+    A: Amount,
+    const N: usize,
+    S: LevelStorage<A> = DenseLevels<A>,
+    T: TopLevels<P, A, N> = VecLevels<P, A>,
+    O: LevelObserver<P, A> = (),
+> {
+    book: OrderBook<P, A, N, S, T, O>,
+    pending: L2DeltaAggregator<P, A>,
+    pending_ts: Option<u64>,
+}
+
+impl<
+        P: Price,
+        A: Amount,
+        const N: usize,
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, N>,
+        O: LevelObserver<P, A>,
+    > ConflatedBook<P, A, N, S, T, O>
+{
+    pub fn new(book: OrderBook<P, A, N, S, T, O>, reserve_size: usize) -> Self {
+        ConflatedBook {
+            book,
+            pending: L2DeltaAggregator::new(reserve_size),
+            pending_ts: None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, side: &Side, l2_delta: &L2Delta<P, A>) {
+        self.pending.push(side, l2_delta);
+    }
+
+    /// The orders are updated right away, only the book waits for the boundary
+    #[inline(always)]
+    pub fn push_l3_deltas(&mut self, orders: &mut OrderStore<P, A>, l3_deltas: &[L3Delta<P, A>]) {
+        self.pending.push_l3_deltas(orders, l3_deltas);
+    }
+
+    /// Commits the pending transaction if `ts` starts a new one, see `commit`
+    #[inline(always)]
+    pub fn start_at(&mut self, ts: u64) -> bool {
+        let committed = match self.pending_ts {
+            Some(pending_ts) if pending_ts != ts => self.commit(),
+            _ => false,
+        };
+        self.pending_ts = Some(ts);
+
+        committed
+    }
+
+    /// Applies the pending updates, one apply call per side.
+    /// Returns whether the top of either side changed, see `L2BookBuilder::top_changes` for the details.
+    #[inline(always)]
+    pub fn commit(&mut self) -> bool {
+        self.pending_ts = None;
+
+        if self.pending.is_empty() {
+            return false;
+        }
+
+        self.book.flush_l2_deltas(&mut self.pending);
+
+        !self.book.bid().top_changes().is_empty() || !self.book.ask().top_changes().is_empty()
+    }
+
+    #[inline(always)]
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Drops the pending updates, e.g. on a gap
+    #[inline(always)]
+    pub fn discard(&mut self) {
+        self.pending.clear();
+        self.pending_ts = None;
+    }
+
+    /// Pending updates are dropped, the snapshot supersedes them
+    #[inline(always)]
+    pub fn apply_l2_snapshot(&mut self, bids: &[Level<P, A>], asks: &[Level<P, A>]) {
+        self.discard();
+        self.book.apply_l2_snapshot(bids, asks);
+    }
+
+    /// State as of the last committed transaction
+    #[inline(always)]
+    pub fn book(&self) -> &OrderBook<P, A, N, S, T, O> {
+        &self.book
+    }
+}
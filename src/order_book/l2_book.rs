@@ -1,131 +1,264 @@
+use super::{TopLevels, VecLevels};
 use crate::common::intrinsics::*;
 use crate::common::types::{Amount, Level, Price};
+use crate::common::TickSchedule;
 
+/// Depth up to which positions are found by counting the better levels with SIMD compares
+/// (see `Price::count_better`), deeper books use a binary search.
+const LINEAR_SEARCH_MAX_DEPTH: usize = 64;
+
+/// Depth parameter of books whose depth is chosen at runtime, see `L2Book::with_depth`
+pub const DYNAMIC_DEPTH: usize = 0;
+
+/// Change of the top of an `L2Book`, positions are the ones at the time of the change
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TopChange<P, A> {
+    Insert {
+        pos: usize,
+        level: Level<P, A>,
+    },
+    /// Deleted, or fell out of a full top
+    Remove {
+        pos: usize,
+        level: Level<P, A>,
+    },
+    Update {
+        pos: usize,
+        prev: Level<P, A>,
+        level: Level<P, A>,
+    },
+}
+
+/// `T` is the storage of the levels, e.g. `ArrayLevels` to keep the top inline.
 #[derive(Debug, Clone)]
-pub struct L2Book<P, const N: usize, const REVERSE: bool> {
-    levels: Vec<P>,
-    tick_size: P,
+pub struct L2Book<P, A, const N: usize, const REVERSE: bool, T = VecLevels<P, A>> {
+    levels: T,
+    tick_schedule: TickSchedule<P>,
+    _marker: std::marker::PhantomData<A>,
 }
 
 /// Cases:
 ///
 /// - Amount > 0 (Upsert):
-///     1. Find the position for insertion/update. If the position is beyond the top (None), then take no action.
-///     2. If we find the exact price, simply return.
+///     1. Find the position for insertion/update (see `position`). If it is past the worst level of a full top, then take no action.
+///     2. If we find the exact price, update the amount and return.
 ///     3. Shift to the right from the insertion position.
 ///     4. Insert the new price.
 ///
 /// - Amount == 0 (Delete):
-///     1. Find the position for the price. If the position is beyond the top (None), take no action.
+///     1. Find the position for the price. If the position is beyond the top, take no action.
 ///        Also, find the position of the worst price in the top.
 ///     2. If `top[pos] != price`, return.
 ///     3. If the position of the worst price is None, it means the top is empty - return.
 ///     4. When we delete the price, there will be a shift to the left,
 ///        leaving an empty spot at the position of the worst price. Therefore, we need to ask PriceMap for the next worst price with amount > 0.
 ///     5. Insert the next worst price in the empty spot.
-impl<P: Price, const N: usize, const REVERSE: bool> L2Book<P, N, REVERSE> {
-    pub fn new(tick_size: P) -> Self {
+impl<P: Price, A: Amount, const N: usize, const REVERSE: bool, T: TopLevels<P, A, N>>
+    L2Book<P, A, N, REVERSE, T>
+{
+    /// Not available for `N = DYNAMIC_DEPTH`, see `with_depth`
+    pub fn new(tick_schedule: impl Into<TickSchedule<P>>) -> Self {
+        const { assert!(N != DYNAMIC_DEPTH, "Use with_depth for DYNAMIC_DEPTH") };
+        Self::with_depth(tick_schedule, N)
+    }
+
+    /// With `N = DYNAMIC_DEPTH` the depth is taken from `depth`, otherwise it has to match `N`
+    pub fn with_depth(tick_schedule: impl Into<TickSchedule<P>>, depth: usize) -> Self {
+        assert!(
+            depth > 0 && (N == DYNAMIC_DEPTH || depth == N),
+            "Unsupported book depth: N=[{}], depth=[{}]",
+            N,
+            depth
+        );
+
         L2Book {
-            levels: Vec::with_capacity(N),
-            tick_size,
+            levels: T::with_depth(depth),
+            tick_schedule: tick_schedule.into(),
+            _marker: std::marker::PhantomData,
         }
     }
 
+    /// Maximum number of levels
     #[inline(always)]
-    pub fn upsert(&mut self, px: P) {
-        let px = P::round_to_tick_size(&px, &self.tick_size);
-        let mut px_pos_opt = self.levels.is_empty().then_some(0);
-
-        for (idx, lvl) in self.levels.iter().enumerate() {
-            if Self::comparator(px, *lvl) {
-                px_pos_opt = Some(idx);
-                break;
-            }
+    pub fn depth(&self) -> usize {
+        if N == DYNAMIC_DEPTH {
+            self.levels.depth()
+        } else {
+            N
         }
+    }
 
-        let px_pos = match px_pos_opt {
-            Some(pos) => pos,
-            None => {
-                self.levels.push(px);
-                return;
-            }
-        };
+    #[inline(always)]
+    pub fn upsert(&mut self, level: Level<P, A>) {
+        self.upsert_with(level, |_| {});
+    }
 
-        if unlikely(self.levels.is_empty()) {
-            self.levels.push(px);
+    /// Changes of the top are reported through `on_change`, levels that don't change are not
+    #[inline(always)]
+    pub fn upsert_with(&mut self, level: Level<P, A>, mut on_change: impl FnMut(TopChange<P, A>)) {
+        let px = self.tick_schedule.round_to_tick_size(&level.px);
+        let level = Level { px, ..level };
+        let px_pos = self.position(&px);
+
+        if px_pos == self.levels.len() {
+            if self.levels.len() < self.depth() {
+                self.levels.insert(px_pos, level);
+                on_change(TopChange::Insert { pos: px_pos, level });
+            }
             return;
         }
 
-        if self.levels[px_pos] == px {
+        let prev = self.levels.as_slice()[px_pos];
+        if prev.px == px {
+            if prev != level {
+                self.levels.as_mut_slice()[px_pos] = level;
+                on_change(TopChange::Update {
+                    pos: px_pos,
+                    prev,
+                    level,
+                });
+            }
             return;
         }
 
-        if likely(self.levels.len() == N) {
-            self.levels.pop();
+        let len = self.levels.len();
+        if len == self.depth() {
+            on_change(TopChange::Remove {
+                pos: len - 1,
+                level: self.levels.as_slice()[len - 1],
+            });
         }
+        self.levels.insert(px_pos, level);
+        on_change(TopChange::Insert { pos: px_pos, level });
+    }
 
-        self.levels.insert(px_pos, px);
+    /// Updates the amount (and order count) of the price if it is in the top, no insertion.
+    #[inline(always)]
+    pub fn update(&mut self, level: Level<P, A>) {
+        self.update_with(level, |_| {});
     }
 
+    /// See `upsert_with`
     #[inline(always)]
-    pub fn delete<A: Amount>(
+    pub fn update_with(&mut self, level: Level<P, A>, mut on_change: impl FnMut(TopChange<P, A>)) {
+        let px = self.tick_schedule.round_to_tick_size(&level.px);
+        let px_pos = self.position(&px);
+
+        if let Some(lvl) = self.levels.as_mut_slice().get_mut(px_pos) {
+            let level = Level { px, ..level };
+            if lvl.px == px && *lvl != level {
+                let prev = std::mem::replace(lvl, level);
+                on_change(TopChange::Update {
+                    pos: px_pos,
+                    prev,
+                    level,
+                });
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn delete(&mut self, px: P, get_next_worst_lvl: impl Fn(&P) -> Option<Level<P, A>>) {
+        self.delete_with(px, get_next_worst_lvl, |_| {});
+    }
+
+    /// See `upsert_with`, the level refilled from `get_next_worst_lvl` is reported as an insertion
+    #[inline(always)]
+    pub fn delete_with(
         &mut self,
         px: P,
         get_next_worst_lvl: impl Fn(&P) -> Option<Level<P, A>>,
+        mut on_change: impl FnMut(TopChange<P, A>),
     ) {
         if unlikely(self.levels.is_empty()) {
             return;
         }
 
-        let px = P::round_to_tick_size(&px, &self.tick_size);
-        let mut px_pos_opt = None;
+        let px = self.tick_schedule.round_to_tick_size(&px);
+        let px_pos = self.position(&px);
         let worst_pos = self.levels.len() - 1;
 
-        for (idx, lvl) in self.levels.iter().enumerate() {
-            if px == *lvl {
-                px_pos_opt = Some(idx);
-                break;
-            }
+        if self
+            .levels
+            .as_slice()
+            .get(px_pos)
+            .is_none_or(|lvl| lvl.px != px)
+        {
+            return;
         }
 
-        let px_pos = match px_pos_opt {
-            Some(pos) => pos,
-            None => return,
-        };
-
-        let worst_lvl = self.levels[worst_pos];
+        let worst_lvl = self.levels.as_slice()[worst_pos];
 
+        on_change(TopChange::Remove {
+            pos: px_pos,
+            level: self.levels.as_slice()[px_pos],
+        });
         self.levels.remove(px_pos);
 
-        if self.levels.len() < N - 1 {
+        if self.levels.len() < self.depth() - 1 {
             return;
         }
 
-        let lvl = match get_next_worst_lvl(&worst_lvl) {
+        let lvl = match get_next_worst_lvl(&worst_lvl.px) {
             Some(lvl) => lvl,
             None => return,
         };
 
-        self.levels
-            .push(P::round_to_tick_size(&lvl.px, &self.tick_size));
+        let pos = self.levels.len();
+        let level = Level {
+            px: self.tick_schedule.round_to_tick_size(&lvl.px),
+            ..lvl
+        };
+        self.levels.insert(pos, level);
+        on_change(TopChange::Insert { pos, level });
+    }
+
+    /// Position of the first level that is not better than `px`.
+    /// The search is chosen by `N` at compile time, by the depth for `DYNAMIC_DEPTH`.
+    #[inline(always)]
+    fn position(&self, px: &P) -> usize {
+        let levels = self.levels.as_slice();
+        let is_shallow = if N == DYNAMIC_DEPTH {
+            self.depth() <= LINEAR_SEARCH_MAX_DEPTH
+        } else {
+            const { N <= LINEAR_SEARCH_MAX_DEPTH }
+        };
+
+        if is_shallow {
+            P::count_better::<REVERSE, A>(levels, px)
+        } else {
+            levels.partition_point(|lvl| Self::is_better(&lvl.px, px))
+        }
     }
 
     #[inline(always)]
-    fn comparator(a: P, b: P) -> bool {
+    fn is_better(a: &P, b: &P) -> bool {
         if REVERSE {
-            a >= b
+            a > b
         } else {
-            a <= b
+            a < b
         }
     }
 
     #[inline(always)]
-    pub fn levels(&self) -> &[P] {
-        &self.levels
+    pub fn levels(&self) -> &[Level<P, A>] {
+        self.levels.as_slice()
     }
 
     #[inline(always)]
     pub fn clear(&mut self) {
         self.levels.clear();
     }
+
+    /// Every level is reported as removed, best first
+    #[inline(always)]
+    pub fn clear_with(&mut self, mut on_change: impl FnMut(TopChange<P, A>)) {
+        for level in self.levels.as_slice() {
+            on_change(TopChange::Remove {
+                pos: 0,
+                level: *level,
+            });
+        }
+        self.levels.clear();
+    }
 }
@@ -1,72 +1,287 @@
 use super::L2Book;
+use super::LevelIter;
+use super::LevelObserver;
+use super::OrderStore;
 use super::PriceLevel;
 use super::PriceMap;
-use crate::common::types::{Amount, L2Delta, Level, Price};
+use super::RangeGrowth;
+use super::TopChange;
+use super::{DenseLevels, LevelStorage};
+use super::{TopLevels, VecLevels, DYNAMIC_DEPTH};
+use crate::common::types::{Amount, AmountAnomaly, L2Delta, L3Delta, Level, Price, Side};
+use crate::common::TickSchedule;
 
+/// Walk of one side of the book for a given amount, see `L2BookBuilder::cost_to_fill`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FillEstimate<P, A> {
+    /// Less than requested if the side is thinner than that
+    pub filled_amt: A,
+    pub avg_px: f64,
+    pub best_px: P,
+    /// Price of the last level touched
+    pub worst_px: P,
+}
+
+impl<P: Price, A> FillEstimate<P, A> {
+    /// Distance of the average fill price from the best price, in basis points
+    #[inline(always)]
+    pub fn slippage_bps(&self) -> f64 {
+        let best_px = self.best_px.to_f64();
+        (self.avg_px - best_px).abs() / best_px.abs() * 1e4
+    }
+}
+
+/// Builder with the depth chosen at runtime, see `L2BookBuilder::with_depth`
+pub type DynL2BookBuilder<P, A, const IS_BID: bool, S = DenseLevels<A>> =
+    L2BookBuilder<P, A, DYNAMIC_DEPTH, IS_BID, S>;
+
+/// `S` is the storage of the price map, e.g. `PagedLevels` for very fine tick sizes,
+/// `T` is the storage of the top, e.g. `ArrayLevels` for a book without heap,
+/// `O` is notified of every level change, see `with_observer`.
 #[derive(Debug, Clone)]
-pub struct L2BookBuilder<P: Price, A: Amount, const SIZE: usize, const IS_BID: bool> {
-    price_map: PriceMap<P, A>,
-    l2_book: L2Book<P, SIZE, IS_BID>,
+pub struct L2BookBuilder<
+    P: Price,
+    A: Amount,
+    const SIZE: usize,
+    const IS_BID: bool,
+    S: LevelStorage<A> = DenseLevels<A>,
+    T: TopLevels<P, A, SIZE> = VecLevels<P, A>,
+    O: LevelObserver<P, A> = (),
+> {
+    price_map: PriceMap<P, A, S>,
+    l2_book: L2Book<P, A, SIZE, IS_BID, T>,
+    observer: O,
+    top_changes: Vec<TopChange<P, A>>,
+    prev_best_px: Option<P>,
 }
 
-impl<P: Price, A: Amount, const SIZE: usize, const IS_BID: bool> L2BookBuilder<P, A, SIZE, IS_BID> {
-    pub fn new(start_px: P, end_px: Option<P>, tick_size: P) -> Self {
+impl<
+        P: Price,
+        A: Amount,
+        const SIZE: usize,
+        const IS_BID: bool,
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, SIZE>,
+        O: LevelObserver<P, A>,
+    > L2BookBuilder<P, A, SIZE, IS_BID, S, T, O>
+{
+    const SIDE: Side = if IS_BID { Side::Bid } else { Side::Ask };
+
+    /// `max_ticks` bounds the memory of the price map with a window following the top,
+    /// see `PriceMap`. Not available for `SIZE = DYNAMIC_DEPTH`, see `with_depth`.
+    pub fn new(
+        start_px: P,
+        end_px: Option<P>,
+        tick_schedule: impl Into<TickSchedule<P>>,
+        max_ticks: Option<usize>,
+    ) -> Self
+    where
+        O: Default,
+    {
+        const { assert!(SIZE != DYNAMIC_DEPTH, "Use with_depth for DYNAMIC_DEPTH") };
+        Self::with_depth(start_px, end_px, tick_schedule, max_ticks, SIZE)
+    }
+
+    /// For `SIZE = DYNAMIC_DEPTH` (see `DynL2BookBuilder`), the depth is chosen at runtime
+    pub fn with_depth(
+        start_px: P,
+        end_px: Option<P>,
+        tick_schedule: impl Into<TickSchedule<P>>,
+        max_ticks: Option<usize>,
+        depth: usize,
+    ) -> Self
+    where
+        O: Default,
+    {
+        let tick_schedule = tick_schedule.into();
+
+        L2BookBuilder {
+            price_map: PriceMap::new(start_px, end_px, tick_schedule, max_ticks),
+            l2_book: L2Book::with_depth(tick_schedule, depth),
+            observer: O::default(),
+            top_changes: Vec::new(),
+            prev_best_px: None,
+        }
+    }
+
+    /// Replaces the observer, it is told about the current levels first
+    pub fn with_observer<U: LevelObserver<P, A>>(
+        self,
+        mut observer: U,
+    ) -> L2BookBuilder<P, A, SIZE, IS_BID, S, T, U> {
+        observer.on_clear();
+        for lvl in self.levels() {
+            observer.on_level_change(
+                lvl.px,
+                &PriceLevel::default(),
+                &PriceLevel {
+                    amt: lvl.amt,
+                    order_count: lvl.order_count,
+                },
+            );
+        }
+
         L2BookBuilder {
-            price_map: PriceMap::new(start_px, end_px, tick_size),
-            l2_book: L2Book::new(tick_size),
+            price_map: self.price_map,
+            l2_book: self.l2_book,
+            observer,
+            top_changes: self.top_changes,
+            prev_best_px: self.prev_best_px,
+        }
+    }
+
+    #[inline(always)]
+    pub fn observer(&self) -> &O {
+        &self.observer
+    }
+
+    /// See `PriceMap::with_growth`
+    pub fn with_growth(self, growth: RangeGrowth) -> Self {
+        L2BookBuilder {
+            price_map: self.price_map.with_growth(growth),
+            ..self
         }
     }
 
     #[inline(always)]
     pub fn apply_l2_snapshot(&mut self, l2_snapshot: &[Level<P, A>]) {
+        self.begin_top_changes();
+
         self.price_map.clear();
-        self.l2_book.clear();
+        self.l2_book
+            .clear_with(|change| self.top_changes.push(change));
+        self.observer.on_clear();
 
-        self.apply_l2_upserts(l2_snapshot);
+        self.upsert_levels(l2_snapshot);
     }
 
+    /// Order counts are taken as is, zero for venues that don't publish them
     #[inline(always)]
     pub fn apply_l2_upserts(&mut self, l2_updates: &[Level<P, A>]) {
-        for Level { px, amt } in l2_updates.iter() {
-            let level = self.price_map.get_mut(*px);
+        self.begin_top_changes();
+        self.upsert_levels(l2_updates);
+    }
 
-            let was_zero = level.amt.is_zero() && !amt.is_zero();
-            level.amt = *amt;
-            let became_zero = !was_zero && level.amt.is_zero();
+    /// Anomalies are logged, see `apply_l2_deltas_with`
+    #[inline(always)]
+    pub fn apply_l2_deltas(&mut self, l2_deltas: &[L2Delta<P, A>]) {
+        self.apply_l2_deltas_with(l2_deltas, AmountAnomaly::log);
+    }
 
-            if was_zero {
-                self.l2_book.upsert(*px);
-            } else if became_zero {
-                self.l2_book
-                    .delete(*px, |worst_px| self.price_map.next_px::<IS_BID>(worst_px));
-            }
+    /// Deltas that don't fit the level's amount clamp it to zero and are reported through `on_anomaly`
+    #[inline(always)]
+    pub fn apply_l2_deltas_with(
+        &mut self,
+        l2_deltas: &[L2Delta<P, A>],
+        mut on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        self.begin_top_changes();
+
+        for l2_delta in l2_deltas.iter() {
+            self.apply_l2_delta(l2_delta, &mut on_anomaly);
         }
     }
 
+    /// Applies order-level events, keeping the orders in `orders`.
+    ///
+    /// The store holds the orders of this side only, events of the opposite side are skipped.
+    /// For both sides sharing a store use `OrderBook::apply_l3_deltas`: an event may touch both
+    /// (e.g. an add reusing the ID of an order on the other side), so it has to be resolved once.
     #[inline(always)]
-    pub fn apply_l2_deltas(&mut self, l2_deltas: &[L2Delta<P, A>]) {
-        for L2Delta { px, amt_delta } in l2_deltas.iter() {
-            let level = self.price_map.get_mut(*px);
+    pub fn apply_l3_deltas(&mut self, orders: &mut OrderStore<P, A>, l3_deltas: &[L3Delta<P, A>]) {
+        self.apply_l3_deltas_with(orders, l3_deltas, AmountAnomaly::log);
+    }
 
-            let was_zero = level.amt.is_zero();
-            level.amt = level.amt.apply_delta(amt_delta);
-            let became_zero = level.amt.is_zero();
+    /// See `apply_l2_deltas_with`
+    #[inline(always)]
+    pub fn apply_l3_deltas_with(
+        &mut self,
+        orders: &mut OrderStore<P, A>,
+        l3_deltas: &[L3Delta<P, A>],
+        mut on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        self.begin_top_changes();
 
-            if was_zero {
-                self.l2_book.upsert(*px);
-            } else if became_zero {
-                self.l2_book
-                    .delete(*px, |worst_px| self.price_map.next_px::<IS_BID>(worst_px));
+        for l3_delta in l3_deltas.iter() {
+            if orders.side_of(l3_delta) != Some(Self::SIDE) {
+                continue;
+            }
+
+            let mut overfill = None;
+
+            orders.apply_with(
+                l3_delta,
+                |side, l2_delta| {
+                    if *side == Self::SIDE {
+                        self.apply_l2_delta(l2_delta, &mut on_anomaly)
+                    }
+                },
+                |anomaly| overfill = Some(*anomaly),
+            );
+
+            if let Some(anomaly) = overfill {
+                on_anomaly(&anomaly);
             }
         }
     }
 
+    /// Changes of the top made by the last apply call, in order.
+    /// Levels beyond the depth of the book don't show up here.
+    #[inline(always)]
+    pub fn top_changes(&self) -> &[TopChange<P, A>] {
+        &self.top_changes
+    }
+
+    /// Whether the last apply call moved the best price, e.g. to publish the BBO only when needed
+    #[inline(always)]
+    pub fn best_px_changed(&self) -> bool {
+        self.prev_best_px != self.l2_book.levels().first().map(|lvl| lvl.px)
+    }
+
     pub fn top_levels_from_map<const N: usize>(&self) -> [Option<(P, PriceLevel<A>)>; N] {
         self.price_map.top_levels::<N, IS_BID>()
     }
 
+    /// See `PriceMap::top_levels_into`
+    pub fn top_levels_from_map_into(&self, depth: usize, top: &mut Vec<(P, PriceLevel<A>)>) {
+        self.price_map.top_levels_into::<IS_BID>(depth, top)
+    }
+
+    /// All non-empty levels from the best price outward, not limited by the depth of the book
     #[inline(always)]
-    pub fn book(&self) -> &L2Book<P, SIZE, IS_BID> {
+    pub fn levels(&self) -> LevelIter<'_, P, A, S, IS_BID> {
+        self.price_map.iter::<IS_BID>()
+    }
+
+    /// Levels at most `ticks` away from the best price
+    pub fn levels_within_ticks(&self, ticks: u64) -> impl Iterator<Item = Level<P, A>> + '_ {
+        let best_tick_idx = self
+            .levels()
+            .next()
+            .map(|lvl| self.price_map.tick_idx(&lvl.px));
+
+        self.levels().take_while(move |lvl| {
+            best_tick_idx
+                .is_some_and(|best| self.price_map.tick_idx(&lvl.px).abs_diff(best) <= ticks)
+        })
+    }
+
+    /// Total amount at `px` and better prices
+    pub fn cumulative_amount(&self, px: P) -> A {
+        let mut amt = A::zero();
+
+        for lvl in self
+            .levels()
+            .take_while(|lvl| if IS_BID { lvl.px >= px } else { lvl.px <= px })
+        {
+            amt += lvl.amt;
+        }
+
+        amt
+    }
+
+    #[inline(always)]
+    pub fn book(&self) -> &L2Book<P, A, SIZE, IS_BID, T> {
         &self.l2_book
     }
 
@@ -74,4 +289,200 @@ impl<P: Price, A: Amount, const SIZE: usize, const IS_BID: bool> L2BookBuilder<P
     pub fn get_level(&self, px: P) -> PriceLevel<A> {
         self.price_map.get_immut(px)
     }
+
+    /// Number of levels spilled out of the price map window
+    #[inline(always)]
+    pub fn overflow_len(&self) -> usize {
+        self.price_map.overflow_len()
+    }
+
+    #[inline(always)]
+    fn upsert_levels(&mut self, l2_updates: &[Level<P, A>]) {
+        for update in l2_updates.iter() {
+            let Level {
+                px,
+                amt,
+                order_count,
+            } = *update;
+            let mut level = self.price_map.get_mut(px);
+
+            let prev = *level;
+            let was_zero = level.amt.is_zero() && !amt.is_zero();
+            level.amt = amt;
+            level.order_count = if amt.is_zero() { 0 } else { order_count };
+            let became_zero = !was_zero && level.amt.is_zero();
+            let next = *level;
+            drop(level);
+
+            self.observer.on_level_change(px, &prev, &next);
+
+            let on_change = |change| self.top_changes.push(change);
+            if was_zero {
+                self.l2_book.upsert_with(*update, on_change);
+            } else if became_zero {
+                self.l2_book.delete_with(
+                    px,
+                    |worst_px| self.price_map.next_px::<IS_BID>(worst_px),
+                    on_change,
+                );
+            } else {
+                self.l2_book.update_with(*update, on_change);
+            }
+
+            self.recenter();
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn apply_l2_delta(
+        &mut self,
+        l2_delta: &L2Delta<P, A>,
+        on_anomaly: &mut impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        let L2Delta {
+            px,
+            amt_delta,
+            order_count_delta,
+        } = *l2_delta;
+        let mut level = self.price_map.get_mut(px);
+
+        let prev = *level;
+        let prev_amt = level.amt;
+        level.amt = match prev_amt.checked_apply_delta(&amt_delta) {
+            Some(amt) => amt,
+            None => {
+                on_anomaly(&AmountAnomaly {
+                    side: Self::SIDE,
+                    px,
+                    prev_amt,
+                    amt_delta,
+                });
+                A::zero()
+            }
+        };
+        level.order_count = if level.amt.is_zero() {
+            0
+        } else {
+            level.order_count.saturating_add_signed(order_count_delta)
+        };
+        let updated = Level {
+            px,
+            amt: level.amt,
+            order_count: level.order_count,
+        };
+        let next = *level;
+        drop(level);
+
+        self.observer.on_level_change(px, &prev, &next);
+
+        let on_change = |change| self.top_changes.push(change);
+        if prev_amt.is_zero() {
+            if !updated.amt.is_zero() {
+                self.l2_book.upsert_with(updated, on_change);
+            }
+        } else if updated.amt.is_zero() {
+            self.l2_book.delete_with(
+                px,
+                |worst_px| self.price_map.next_px::<IS_BID>(worst_px),
+                on_change,
+            );
+        } else {
+            self.l2_book.update_with(updated, on_change);
+        }
+
+        self.recenter();
+    }
+
+    #[inline(always)]
+    pub(crate) fn begin_top_changes(&mut self) {
+        self.top_changes.clear();
+        self.prev_best_px = self.l2_book.levels().first().map(|lvl| lvl.px);
+    }
+
+    #[inline(always)]
+    fn recenter(&mut self) {
+        if let Some(top) = self.l2_book.levels().first() {
+            self.price_map.recenter(top.px);
+        }
+    }
+}
+
+impl<
+        P: Price,
+        A: Amount,
+        const SIZE: usize,
+        const IS_BID: bool,
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, SIZE>,
+        O: LevelObserver<P, A>,
+    > L2BookBuilder<P, A, SIZE, IS_BID, S, T, O>
+{
+    /// Levels at most `bps` basis points away from the best price
+    pub fn levels_within_bps(&self, bps: f64) -> impl Iterator<Item = Level<P, A>> + '_ {
+        let best_px = self.levels().next().map(|lvl| lvl.px.to_f64());
+
+        self.levels().take_while(move |lvl| {
+            best_px.is_some_and(|best| (lvl.px.to_f64() - best).abs() <= best.abs() * bps / 1e4)
+        })
+    }
+
+    /// How much can be taken before the price moves more than `bps` basis points
+    pub fn amount_within_bps(&self, bps: f64) -> A {
+        let mut amt = A::zero();
+
+        for lvl in self.levels_within_bps(bps) {
+            amt += lvl.amt;
+        }
+
+        amt
+    }
+}
+
+impl<
+        P: Price,
+        A: Amount,
+        const SIZE: usize,
+        const IS_BID: bool,
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, SIZE>,
+        O: LevelObserver<P, A>,
+    > L2BookBuilder<P, A, SIZE, IS_BID, S, T, O>
+{
+    /// Takes `amt` from the best price outward, e.g. the cost to buy `amt` on the ask side.
+    /// `None` for an empty side or a zero amount.
+    pub fn cost_to_fill(&self, amt: A) -> Option<FillEstimate<P, A>> {
+        let mut levels = self.levels();
+        let best = levels.next()?;
+
+        let mut remaining = amt;
+        let mut notional = 0.0;
+        let mut worst_px = best.px;
+
+        for lvl in std::iter::once(best).chain(levels) {
+            if remaining.is_zero() {
+                break;
+            }
+
+            let taken = if lvl.amt < remaining {
+                lvl.amt
+            } else {
+                remaining
+            };
+            notional += lvl.px.to_f64() * taken.to_f64();
+            remaining = remaining - taken;
+            worst_px = lvl.px;
+        }
+
+        let filled_amt = amt - remaining;
+        if filled_amt.is_zero() {
+            return None;
+        }
+
+        Some(FillEstimate {
+            filled_amt,
+            avg_px: notional / filled_amt.to_f64(),
+            best_px: best.px,
+            worst_px,
+        })
+    }
 }
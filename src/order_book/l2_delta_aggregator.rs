@@ -0,0 +1,108 @@
+use super::{L2BookBuilder, LevelObserver, LevelStorage, OrderStore, TopLevels};
+use crate::common::types::{Amount, L2Delta, L3Delta, Price, Side};
+
+/// Collects the deltas of a message/packet and nets them per (side, price),
+/// so every touched level is updated once (see docs/l2_book_from_l3.md, Option 1).
+///
+/// Deltas are sorted by price before applying, so the book is walked in one direction.
+#[derive(Debug, Clone)]
+pub struct L2DeltaAggregator<P: Price, A: Amount> {
+    bids: Vec<L2Delta<P, A>>,
+    asks: Vec<L2Delta<P, A>>,
+}
+
+impl<P: Price, A: Amount> L2DeltaAggregator<P, A> {
+    pub fn new(reserve_size: usize) -> Self {
+        L2DeltaAggregator {
+            bids: Vec::with_capacity(reserve_size),
+            asks: Vec::with_capacity(reserve_size),
+        }
+    }
+
+    #[inline(always)]
+    pub fn push(&mut self, side: &Side, l2_delta: &L2Delta<P, A>) {
+        match side {
+            Side::Bid => self.bids.push(*l2_delta),
+            Side::Ask => self.asks.push(*l2_delta),
+        }
+    }
+
+    #[inline(always)]
+    pub fn push_l3_deltas(&mut self, orders: &mut OrderStore<P, A>, l3_deltas: &[L3Delta<P, A>]) {
+        for l3_delta in l3_deltas.iter() {
+            orders.apply(l3_delta, |side, l2_delta| self.push(side, l2_delta));
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// Applies the netted deltas, one `apply_l2_deltas` call per side, and resets the batch.
+    /// Sides without deltas are applied too, so their `top_changes` are of this batch.
+    #[inline(always)]
+    pub fn flush<const SIZE: usize, S, T, O>(
+        &mut self,
+        bid: &mut L2BookBuilder<P, A, SIZE, true, S, T, O>,
+        ask: &mut L2BookBuilder<P, A, SIZE, false, S, T, O>,
+    ) where
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, SIZE>,
+        O: LevelObserver<P, A>,
+    {
+        Self::net(&mut self.bids);
+        bid.apply_l2_deltas(&self.bids);
+        self.bids.clear();
+
+        Self::net(&mut self.asks);
+        ask.apply_l2_deltas(&self.asks);
+        self.asks.clear();
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    /// Sorts by price and collapses deltas of the same price into one,
+    /// dropping the ones netted to zero in both the amount and the order count.
+    #[inline(always)]
+    fn net(l2_deltas: &mut Vec<L2Delta<P, A>>) {
+        if l2_deltas.len() <= 1 {
+            return;
+        }
+
+        l2_deltas.sort_unstable_by(|a, b| a.px.cmp_px(&b.px));
+
+        let zero = A::zero().as_delta();
+        let is_netted = |l2_delta: &L2Delta<P, A>| {
+            l2_delta.amt_delta == zero && l2_delta.order_count_delta == 0
+        };
+        let mut len = 0;
+
+        for idx in 0..l2_deltas.len() {
+            let l2_delta = l2_deltas[idx];
+
+            if len > 0 && l2_deltas[len - 1].px == l2_delta.px {
+                let last = &mut l2_deltas[len - 1];
+                last.amt_delta = last.amt_delta + l2_delta.amt_delta;
+                last.order_count_delta += l2_delta.order_count_delta;
+            } else {
+                if len > 0 && is_netted(&l2_deltas[len - 1]) {
+                    len -= 1;
+                }
+
+                l2_deltas[len] = l2_delta;
+                len += 1;
+            }
+        }
+
+        if len > 0 && is_netted(&l2_deltas[len - 1]) {
+            len -= 1;
+        }
+
+        l2_deltas.truncate(len);
+    }
+}
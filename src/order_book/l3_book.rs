@@ -0,0 +1,486 @@
+use super::PriceHasher;
+use crate::common::{
+    intrinsics::*,
+    types::{Amount, AmountAnomaly, L2Delta, Price, Side},
+    ObjectPool, TickSchedule,
+};
+
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct L3Order<P, A> {
+    pub id: u64,
+    pub side: Side,
+    pub px: P,
+    pub amt: A,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct QueuePosition<A> {
+    pub orders_ahead: usize,
+    pub amt_ahead: A,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OrderNode<P, A> {
+    order: L3Order<P, A>,
+    prev: usize,
+    next: usize,
+}
+
+impl<P: Default, A: Default> Default for OrderNode<P, A> {
+    fn default() -> Self {
+        OrderNode {
+            order: L3Order::default(),
+            prev: NIL,
+            next: NIL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OrderQueue<A> {
+    head: usize,
+    tail: usize,
+    amt: A,
+    cnt: usize,
+}
+
+impl<A: Amount> OrderQueue<A> {
+    #[inline(always)]
+    fn empty() -> Self {
+        OrderQueue {
+            head: NIL,
+            tail: NIL,
+            amt: A::zero(),
+            cnt: 0,
+        }
+    }
+}
+
+/// Market-by-order book: every order sits in a FIFO queue of its price level,
+/// so the book keeps price-time priority on both sides.
+///
+/// - Order nodes live in an `ObjectPool` and are linked into per-level queues by index.
+/// - Entry IDs are mapped to nodes through a vector, since on most venues they are incremental.
+/// - Level queues are vectors indexed by ticks from `start_px`, the range grows like in `PriceMap`.
+/// - Every operation reports the resulting `L2Delta` through `process_l2_delta`,
+///   so the book can feed an `L2BookBuilder` of the corresponding side.
+///
+/// Operations on unknown entry IDs are ignored.
+#[derive(Debug, Clone)]
+pub struct L3Book<P: Price, A: Amount> {
+    nodes: ObjectPool<OrderNode<P, A>>,
+    order_idx: Vec<usize>,
+    px_hasher: PriceHasher<P>,
+    bids: Vec<OrderQueue<A>>,
+    asks: Vec<OrderQueue<A>>,
+    tick_schedule: TickSchedule<P>,
+}
+
+impl<P: Price, A: Amount> L3Book<P, A> {
+    pub fn new(
+        start_px: P,
+        tick_schedule: impl Into<TickSchedule<P>>,
+        reserve_size: usize,
+    ) -> Self {
+        let tick_schedule = tick_schedule.into();
+
+        L3Book {
+            nodes: ObjectPool::new(reserve_size),
+            order_idx: Vec::with_capacity(reserve_size),
+            px_hasher: PriceHasher::new(start_px, tick_schedule),
+            bids: Vec::new(),
+            asks: Vec::new(),
+            tick_schedule,
+        }
+    }
+
+    #[inline(always)]
+    pub fn add(
+        &mut self,
+        id: u64,
+        side: Side,
+        px: P,
+        amt: A,
+        mut process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+    ) {
+        if unlikely(self.order_node(id).is_some()) {
+            log::warn!("Duplicate order: id=[{}]", id);
+            self.delete(id, &mut process_l2_delta);
+        }
+
+        let px = self.tick_schedule.round_to_tick_size(&px);
+        let node_idx = self.nodes.allocate();
+        *self.nodes.get_mut(node_idx) = OrderNode {
+            order: L3Order { id, side, px, amt },
+            prev: NIL,
+            next: NIL,
+        };
+        *self.order_slot_mut(id) = node_idx;
+
+        self.push_back(node_idx);
+
+        process_l2_delta(
+            &side,
+            &L2Delta {
+                px,
+                amt_delta: amt.as_delta(),
+                order_count_delta: 1,
+            },
+        );
+    }
+
+    /// Sets a new amount for the order.
+    /// Decreasing the amount keeps the queue position, increasing it moves the order to the back.
+    #[inline(always)]
+    pub fn modify(
+        &mut self,
+        id: u64,
+        amt: A,
+        mut process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+    ) {
+        let node_idx = match self.order_node(id) {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        if amt.is_zero() {
+            self.delete(id, process_l2_delta);
+            return;
+        }
+
+        let order = self.nodes.get(node_idx).order;
+
+        if amt > order.amt {
+            self.unlink(node_idx);
+            self.nodes.get_mut(node_idx).order.amt = amt;
+            self.push_back(node_idx);
+
+            process_l2_delta(
+                &order.side,
+                &L2Delta::new(order.px, (amt - order.amt).as_delta()),
+            );
+        } else {
+            self.reduce(
+                node_idx,
+                order.amt - amt,
+                process_l2_delta,
+                AmountAnomaly::log,
+            );
+        }
+    }
+
+    /// Overfills are logged, see `execute_with`
+    #[inline(always)]
+    pub fn execute(
+        &mut self,
+        id: u64,
+        executed: A,
+        process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+    ) {
+        self.execute_with(id, executed, process_l2_delta, AmountAnomaly::log);
+    }
+
+    /// More than the order's amount removes it and is reported through `on_anomaly`
+    #[inline(always)]
+    pub fn execute_with(
+        &mut self,
+        id: u64,
+        executed: A,
+        process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+        on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        if let Some(node_idx) = self.order_node(id) {
+            self.reduce(node_idx, executed, process_l2_delta, on_anomaly);
+        }
+    }
+
+    /// Overfills are logged, see `cancel_with`
+    #[inline(always)]
+    pub fn cancel(
+        &mut self,
+        id: u64,
+        cancelled: A,
+        process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+    ) {
+        self.cancel_with(id, cancelled, process_l2_delta, AmountAnomaly::log);
+    }
+
+    /// See `execute_with`
+    #[inline(always)]
+    pub fn cancel_with(
+        &mut self,
+        id: u64,
+        cancelled: A,
+        process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+        on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        if let Some(node_idx) = self.order_node(id) {
+            self.reduce(node_idx, cancelled, process_l2_delta, on_anomaly);
+        }
+    }
+
+    #[inline(always)]
+    pub fn delete(&mut self, id: u64, mut process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>)) {
+        let node_idx = match self.order_node(id) {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        let order = self.remove(node_idx);
+
+        process_l2_delta(
+            &order.side,
+            &L2Delta {
+                px: order.px,
+                amt_delta: -order.amt.as_delta(),
+                order_count_delta: -1,
+            },
+        );
+    }
+
+    /// Replaces the order with a new one on the same side, the new order loses priority.
+    #[inline(always)]
+    pub fn replace(
+        &mut self,
+        id: u64,
+        new_id: u64,
+        px: P,
+        amt: A,
+        mut process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+    ) {
+        let node_idx = match self.order_node(id) {
+            Some(idx) => idx,
+            None => return,
+        };
+
+        let side = self.nodes.get(node_idx).order.side;
+
+        self.delete(id, &mut process_l2_delta);
+        self.add(new_id, side, px, amt, &mut process_l2_delta);
+    }
+
+    #[inline(always)]
+    pub fn order(&self, id: u64) -> Option<&L3Order<P, A>> {
+        self.order_node(id)
+            .map(|node_idx| &self.nodes.get(node_idx).order)
+    }
+
+    /// Orders at the price level in priority order.
+    pub fn level_orders(&self, side: Side, px: P) -> impl Iterator<Item = &L3Order<P, A>> {
+        let head = self.queue(side, &px).map_or(NIL, |queue| queue.head);
+
+        std::iter::successors((head != NIL).then_some(head), |node_idx| {
+            let next = self.nodes.get(*node_idx).next;
+            (next != NIL).then_some(next)
+        })
+        .map(|node_idx| &self.nodes.get(node_idx).order)
+    }
+
+    #[inline(always)]
+    pub fn level_amount(&self, side: Side, px: P) -> A {
+        self.queue(side, &px).map_or(A::zero(), |queue| queue.amt)
+    }
+
+    #[inline(always)]
+    pub fn level_order_count(&self, side: Side, px: P) -> usize {
+        self.queue(side, &px).map_or(0, |queue| queue.cnt)
+    }
+
+    /// Number of orders and amount ahead of the order in its level queue.
+    pub fn queue_position(&self, id: u64) -> Option<QueuePosition<A>> {
+        let node_idx = self.order_node(id)?;
+        let order = &self.nodes.get(node_idx).order;
+
+        let mut position = QueuePosition {
+            orders_ahead: 0,
+            amt_ahead: A::zero(),
+        };
+
+        for ahead in self.level_orders(order.side, order.px) {
+            if ahead.id == id {
+                return Some(position);
+            }
+
+            position.orders_ahead += 1;
+            position.amt_ahead += ahead.amt;
+        }
+
+        None
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.nodes = ObjectPool::new(self.order_idx.capacity());
+        self.order_idx.clear();
+        self.bids.fill(OrderQueue::empty());
+        self.asks.fill(OrderQueue::empty());
+    }
+
+    #[inline(always)]
+    fn reduce(
+        &mut self,
+        node_idx: usize,
+        amt: A,
+        mut process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+        mut on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        let order = self.nodes.get(node_idx).order;
+
+        let (reduced, order_count_delta) = if unlikely(amt >= order.amt) {
+            if unlikely(amt > order.amt) {
+                on_anomaly(&AmountAnomaly {
+                    side: order.side,
+                    px: order.px,
+                    prev_amt: order.amt,
+                    amt_delta: -amt.as_delta(),
+                });
+            }
+
+            self.remove(node_idx);
+            (order.amt, -1)
+        } else {
+            self.nodes.get_mut(node_idx).order.amt = order.amt - amt;
+            let queue = self.queue_mut(order.side, &order.px);
+            queue.amt = queue.amt - amt;
+            (amt, 0)
+        };
+
+        process_l2_delta(
+            &order.side,
+            &L2Delta {
+                px: order.px,
+                amt_delta: -reduced.as_delta(),
+                order_count_delta,
+            },
+        );
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, node_idx: usize) -> L3Order<P, A> {
+        let order = self.nodes.get(node_idx).order;
+
+        self.unlink(node_idx);
+        *self.order_slot_mut(order.id) = NIL;
+        self.nodes.free(node_idx);
+
+        order
+    }
+
+    #[inline(always)]
+    fn push_back(&mut self, node_idx: usize) {
+        let order = self.nodes.get(node_idx).order;
+        let idx = self.grow_to(&order.px);
+
+        let queue = &mut self.queues_mut(order.side)[idx];
+
+        let tail = queue.tail;
+        queue.tail = node_idx;
+        if tail == NIL {
+            queue.head = node_idx;
+        }
+        queue.amt += order.amt;
+        queue.cnt += 1;
+
+        let node = self.nodes.get_mut(node_idx);
+        node.prev = tail;
+        node.next = NIL;
+
+        if tail != NIL {
+            self.nodes.get_mut(tail).next = node_idx;
+        }
+    }
+
+    #[inline(always)]
+    fn unlink(&mut self, node_idx: usize) {
+        let OrderNode { order, prev, next } = *self.nodes.get(node_idx);
+
+        let queue = self.queue_mut(order.side, &order.px);
+        if prev == NIL {
+            queue.head = next;
+        }
+        if next == NIL {
+            queue.tail = prev;
+        }
+        queue.amt = queue.amt - order.amt;
+        queue.cnt -= 1;
+
+        if prev != NIL {
+            self.nodes.get_mut(prev).next = next;
+        }
+        if next != NIL {
+            self.nodes.get_mut(next).prev = prev;
+        }
+    }
+
+    #[inline(always)]
+    fn order_node(&self, id: u64) -> Option<usize> {
+        match self.order_idx.get(id as usize) {
+            Some(&node_idx) if node_idx != NIL => Some(node_idx),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn order_slot_mut(&mut self, id: u64) -> &mut usize {
+        let id = id as usize;
+
+        if unlikely(self.order_idx.len() <= id) {
+            self.order_idx.resize(id + 1, NIL);
+            log::debug!("Resize triggered: new_len=[{}]", self.order_idx.len());
+        }
+
+        &mut self.order_idx[id]
+    }
+
+    /// Grows the range of both sides to contain the price
+    #[inline(always)]
+    fn grow_to(&mut self, px: &P) -> usize {
+        let (idx, shift) = self.px_hasher.hash(px);
+
+        if unlikely(shift != 0) {
+            for queues in [&mut self.bids, &mut self.asks] {
+                queues.splice(0..0, std::iter::repeat_n(OrderQueue::empty(), shift));
+            }
+            log::debug!("Shift triggered: new_len=[{}]", self.bids.len());
+        }
+
+        if unlikely(idx >= self.bids.len()) {
+            self.bids.resize(idx + 1, OrderQueue::empty());
+            self.asks.resize(idx + 1, OrderQueue::empty());
+            log::debug!("Resize triggered: new_len=[{}]", self.bids.len());
+        }
+
+        idx
+    }
+
+    #[inline(always)]
+    fn queue(&self, side: Side, px: &P) -> Option<&OrderQueue<A>> {
+        let idx = self.px_hasher.try_hash(px)?;
+        self.queues(side).get(idx)
+    }
+
+    /// The level has to hold an order
+    #[inline(always)]
+    fn queue_mut(&mut self, side: Side, px: &P) -> &mut OrderQueue<A> {
+        let idx = self.px_hasher.try_hash(px).unwrap();
+        &mut self.queues_mut(side)[idx]
+    }
+
+    #[inline(always)]
+    fn queues(&self, side: Side) -> &Vec<OrderQueue<A>> {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    #[inline(always)]
+    fn queues_mut(&mut self, side: Side) -> &mut Vec<OrderQueue<A>> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use super::PriceLevel;
+
+use std::fmt::Debug;
+
+/// Notified of every level change of an `L2BookBuilder`, e.g. to maintain derived views incrementally.
+/// `()` observes nothing.
+pub trait LevelObserver<P, A>: Debug + Clone {
+    fn on_level_change(&mut self, px: P, prev: &PriceLevel<A>, level: &PriceLevel<A>);
+
+    /// All levels were removed, e.g. before a snapshot
+    fn on_clear(&mut self);
+}
+
+impl<P, A> LevelObserver<P, A> for () {
+    #[inline(always)]
+    fn on_level_change(&mut self, _px: P, _prev: &PriceLevel<A>, _level: &PriceLevel<A>) {}
+
+    #[inline(always)]
+    fn on_clear(&mut self) {}
+}
@@ -0,0 +1,173 @@
+use super::PriceLevel;
+use crate::common::types::Amount;
+
+use std::collections::VecDeque;
+use std::fmt::Debug;
+
+/// Backing storage of `PriceMap`: levels indexed by ticks from the start of the range.
+pub trait LevelStorage<A>: Debug + Clone {
+    fn with_len(len: usize) -> Self;
+
+    fn len(&self) -> usize;
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Empty level if nothing was ever stored at `idx`
+    fn get(&self, idx: usize) -> PriceLevel<A>;
+
+    fn get_mut(&mut self, idx: usize) -> &mut PriceLevel<A>;
+
+    /// Extends the range upwards to `len` levels
+    fn grow(&mut self, len: usize);
+
+    /// Extends the range downwards by `shift` levels, existing levels move to `idx + shift`
+    fn shift(&mut self, shift: usize);
+
+    /// Shrinks the range from above to `len` levels, the dropped levels have to be empty
+    fn truncate(&mut self, len: usize);
+
+    /// Shrinks the range from below by `count` levels, remaining levels move to `idx - count`.
+    /// The dropped levels have to be empty.
+    fn drop_front(&mut self, count: usize);
+}
+
+/// One contiguous vector, the fastest access but a downward extension is O(range).
+/// The capacity is kept on shrinking, so a bounded range moves without allocations.
+#[derive(Debug, Clone)]
+pub struct DenseLevels<A> {
+    levels: Vec<PriceLevel<A>>,
+}
+
+impl<A: Amount> LevelStorage<A> for DenseLevels<A> {
+    fn with_len(len: usize) -> Self {
+        DenseLevels {
+            levels: vec![PriceLevel::default(); len],
+        }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    #[inline(always)]
+    fn get(&self, idx: usize) -> PriceLevel<A> {
+        self.levels[idx]
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, idx: usize) -> &mut PriceLevel<A> {
+        &mut self.levels[idx]
+    }
+
+    fn grow(&mut self, len: usize) {
+        self.levels.resize(len, PriceLevel::default());
+    }
+
+    fn shift(&mut self, shift: usize) {
+        self.levels
+            .splice(0..0, std::iter::repeat_n(PriceLevel::default(), shift));
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.levels.truncate(len);
+    }
+
+    fn drop_front(&mut self, count: usize) {
+        self.levels.drain(..count);
+    }
+}
+
+/// Two-level page table: pages of `PAGE_SIZE` ticks are allocated on the first write.
+///
+/// Extending the range only touches the page table, so a far price costs O(page)
+/// and there is no copy of the levels on a downward extension.
+/// The first page is partially used, `offset` is the number of ticks before the start of the range.
+#[derive(Debug, Clone)]
+pub struct PagedLevels<A, const PAGE_SIZE: usize = 4096> {
+    pages: VecDeque<Option<Box<[PriceLevel<A>]>>>,
+    offset: usize,
+    len: usize,
+}
+
+impl<A: Amount, const PAGE_SIZE: usize> PagedLevels<A, PAGE_SIZE> {
+    /// Number of allocated pages
+    pub fn page_count(&self) -> usize {
+        self.pages.iter().filter(|page| page.is_some()).count()
+    }
+}
+
+impl<A: Amount, const PAGE_SIZE: usize> LevelStorage<A> for PagedLevels<A, PAGE_SIZE> {
+    fn with_len(len: usize) -> Self {
+        let mut levels = PagedLevels {
+            pages: VecDeque::new(),
+            offset: 0,
+            len: 0,
+        };
+        levels.grow(len);
+        levels
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    fn get(&self, idx: usize) -> PriceLevel<A> {
+        let pos = idx + self.offset;
+
+        match &self.pages[pos / PAGE_SIZE] {
+            Some(page) => page[pos % PAGE_SIZE],
+            None => PriceLevel::default(),
+        }
+    }
+
+    #[inline(always)]
+    fn get_mut(&mut self, idx: usize) -> &mut PriceLevel<A> {
+        let pos = idx + self.offset;
+
+        let page = self.pages[pos / PAGE_SIZE]
+            .get_or_insert_with(|| vec![PriceLevel::default(); PAGE_SIZE].into_boxed_slice());
+
+        &mut page[pos % PAGE_SIZE]
+    }
+
+    fn grow(&mut self, len: usize) {
+        self.pages
+            .resize((len + self.offset).div_ceil(PAGE_SIZE), None);
+        self.len = len;
+    }
+
+    fn shift(&mut self, shift: usize) {
+        if shift > self.offset {
+            let new_pages = (shift - self.offset).div_ceil(PAGE_SIZE);
+
+            for _ in 0..new_pages {
+                self.pages.push_front(None);
+            }
+
+            self.offset += new_pages * PAGE_SIZE;
+        }
+
+        self.offset -= shift;
+        self.len += shift;
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.pages.truncate((len + self.offset).div_ceil(PAGE_SIZE));
+        self.len = len;
+    }
+
+    fn drop_front(&mut self, count: usize) {
+        self.offset += count;
+        self.len -= count;
+
+        let dropped_pages = self.offset / PAGE_SIZE;
+        self.pages.drain(..dropped_pages);
+        self.offset -= dropped_pages * PAGE_SIZE;
+    }
+}
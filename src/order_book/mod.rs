@@ -1,9 +1,35 @@
+mod book;
+mod book_checksum;
+mod bucket_ladder;
+mod conflated_book;
 mod l2_book;
 mod l2_book_builder;
+mod l2_delta_aggregator;
+mod l3_book;
+mod level_observer;
+mod level_storage;
+mod occupancy_index;
+mod order_store;
 mod price_hasher;
 mod price_map;
+mod shared_books;
+mod top_levels;
+mod top_of_book;
 
-pub use l2_book::L2Book;
-pub use l2_book_builder::L2BookBuilder;
-pub use price_hasher::PriceHasher;
-pub use price_map::{PriceLevel, PriceMap};
+pub use book::{BookState, OrderBook};
+pub use book_checksum::{BookChecksum, ChecksumValue, KrakenChecksum, OkxChecksum};
+pub use bucket_ladder::{BucketGrid, BucketLadder};
+pub use conflated_book::ConflatedBook;
+pub use l2_book::{L2Book, TopChange, DYNAMIC_DEPTH};
+pub use l2_book_builder::{DynL2BookBuilder, FillEstimate, L2BookBuilder};
+pub use l2_delta_aggregator::L2DeltaAggregator;
+pub use l3_book::{L3Book, L3Order, QueuePosition};
+pub use level_observer::LevelObserver;
+pub use level_storage::{DenseLevels, LevelStorage, PagedLevels};
+pub use occupancy_index::OccupancyIndex;
+pub use order_store::{OrderEntry, OrderStore};
+pub use price_hasher::{PriceHasher, RangeGrowth};
+pub use price_map::{LevelIter, PriceLevel, PriceLevelMut, PriceMap};
+pub use shared_books::{SharedBooksReader, SharedBooksWriter, SharedValue};
+pub use top_levels::{ArrayLevels, TopLevels, VecLevels};
+pub use top_of_book::TopOfBook;
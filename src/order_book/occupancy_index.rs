@@ -0,0 +1,536 @@
+use std::collections::VecDeque;
+
+const WORD_BITS: usize = u64::BITS as usize;
+const PAGE_BITS: usize = WORD_BITS * WORD_BITS;
+
+/// Occupied indices split into pages of `PAGE_BITS` bits, pages are allocated on the first insert.
+///
+/// Occupied pages are tracked by a hierarchical bitset, so next/previous lookups don't depend on
+/// the distance between the indices. Like `PagedLevels` the first page is partially used,
+/// `offset` is the number of bits before the start of the range: a downward shift only moves
+/// `offset` and prepends empty pages, existing pages are never copied.
+#[derive(Debug, Clone, Default)]
+pub struct OccupancyIndex {
+    pages: VecDeque<Option<Box<Page>>>,
+    occupied_pages: Layers,
+    offset: usize,
+    len: usize,
+}
+
+impl OccupancyIndex {
+    pub fn new(len: usize) -> Self {
+        let mut index = OccupancyIndex::default();
+        index.resize(len);
+        index
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.occupied_pages.is_empty()
+    }
+
+    /// Grows or shrinks the index at the end, bits beyond the new length are dropped.
+    /// Growing only appends empty pages.
+    pub fn resize(&mut self, len: usize) {
+        let end = len + self.offset;
+
+        if len < self.len {
+            self.pages.truncate(end.div_ceil(PAGE_BITS));
+            let last_page_idx = self.pages.len().wrapping_sub(1);
+
+            if let Some(Some(page)) = self.pages.back_mut() {
+                page.truncate(end - last_page_idx * PAGE_BITS);
+
+                if page.is_empty() {
+                    self.occupied_pages.remove(last_page_idx);
+                }
+            }
+        } else {
+            self.pages.resize(end.div_ceil(PAGE_BITS), None);
+        }
+
+        self.occupied_pages.resize(self.pages.len());
+        self.len = len;
+    }
+
+    /// Extends the range downwards by `shift` bits, existing bits move to `idx + shift`.
+    ///
+    /// Costs O(1) while the first page has room, otherwise at least as many empty pages
+    /// as there already are get prepended, so the page index is rebuilt O(log range) times.
+    pub fn shift(&mut self, shift: usize) {
+        if shift > self.offset {
+            let new_pages = (shift - self.offset)
+                .div_ceil(PAGE_BITS)
+                .max(self.pages.len());
+
+            for _ in 0..new_pages {
+                self.pages.push_front(None);
+            }
+
+            let mut occupied_pages = Layers::new(self.pages.len());
+
+            for page_idx in self.occupied_pages.iter() {
+                occupied_pages.insert(page_idx + new_pages);
+            }
+
+            self.occupied_pages = occupied_pages;
+            self.offset += new_pages * PAGE_BITS;
+        }
+
+        self.offset -= shift;
+        self.len += shift;
+    }
+
+    /// Shrinks the range from below by `count` bits, remaining bits move to `idx - count`.
+    /// The dropped bits have to be clear, pages left entirely below the range are freed.
+    pub fn drop_front(&mut self, count: usize) {
+        self.offset += count;
+        self.len -= count;
+
+        let dropped_pages = self.offset / PAGE_BITS;
+        if dropped_pages == 0 {
+            return;
+        }
+
+        self.pages.drain(..dropped_pages);
+        let mut occupied_pages = Layers::new(self.pages.len());
+
+        for page_idx in self.occupied_pages.iter() {
+            occupied_pages.insert(page_idx - dropped_pages);
+        }
+
+        self.occupied_pages = occupied_pages;
+        self.offset -= dropped_pages * PAGE_BITS;
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, idx: usize) -> bool {
+        let pos = idx + self.offset;
+
+        idx < self.len
+            && self.pages[pos / PAGE_BITS]
+                .as_ref()
+                .is_some_and(|page| page.contains(pos % PAGE_BITS))
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, idx: usize) {
+        let pos = idx + self.offset;
+        let page_idx = pos / PAGE_BITS;
+        let page = self.pages[page_idx].get_or_insert_with(Box::default);
+
+        if page.is_empty() {
+            self.occupied_pages.insert(page_idx);
+        }
+
+        page.insert(pos % PAGE_BITS);
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, idx: usize) {
+        let pos = idx + self.offset;
+        let page_idx = pos / PAGE_BITS;
+
+        if let Some(page) = &mut self.pages[page_idx] {
+            page.remove(pos % PAGE_BITS);
+
+            if page.is_empty() {
+                self.occupied_pages.remove(page_idx);
+            }
+        }
+    }
+
+    /// Smallest occupied index greater than `idx`
+    #[inline(always)]
+    pub fn next(&self, idx: usize) -> Option<usize> {
+        self.next_from(idx.checked_add(1)?)
+    }
+
+    /// Largest occupied index less than `idx`
+    #[inline(always)]
+    pub fn prev(&self, idx: usize) -> Option<usize> {
+        self.prev_from(idx.checked_sub(1)?)
+    }
+
+    #[inline(always)]
+    pub fn first(&self) -> Option<usize> {
+        self.next_from(0)
+    }
+
+    #[inline(always)]
+    pub fn last(&self) -> Option<usize> {
+        self.prev_from(self.len.checked_sub(1)?)
+    }
+
+    /// Occupied indices in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.first(), |idx| self.next(*idx))
+    }
+
+    /// Occupied indices in descending order
+    pub fn iter_rev(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.last(), |idx| self.prev(*idx))
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        for page_idx in self.occupied_pages.iter() {
+            if let Some(page) = &mut self.pages[page_idx] {
+                **page = Page::default();
+            }
+        }
+
+        self.occupied_pages.clear();
+    }
+
+    /// Smallest occupied index greater or equal to `start`
+    #[inline(always)]
+    fn next_from(&self, start: usize) -> Option<usize> {
+        if start >= self.len {
+            return None;
+        }
+
+        let pos = start + self.offset;
+        let page_idx = pos / PAGE_BITS;
+
+        let pos = match self.page(page_idx).next_from(pos % PAGE_BITS) {
+            Some(bit) => page_idx * PAGE_BITS + bit,
+            None => {
+                let page_idx = self.occupied_pages.next(page_idx)?;
+                page_idx * PAGE_BITS + self.page(page_idx).next_from(0)?
+            }
+        };
+
+        Some(pos - self.offset)
+    }
+
+    /// Largest occupied index less or equal to `end`
+    #[inline(always)]
+    fn prev_from(&self, end: usize) -> Option<usize> {
+        let pos = end.min(self.len.checked_sub(1)?) + self.offset;
+        let page_idx = pos / PAGE_BITS;
+
+        let pos = match self.page(page_idx).prev_from(pos % PAGE_BITS) {
+            Some(bit) => page_idx * PAGE_BITS + bit,
+            None => {
+                let page_idx = self.occupied_pages.prev(page_idx)?;
+                page_idx * PAGE_BITS + self.page(page_idx).prev_from(PAGE_BITS - 1)?
+            }
+        };
+
+        Some(pos - self.offset)
+    }
+
+    #[inline(always)]
+    fn page(&self, page_idx: usize) -> &Page {
+        self.pages[page_idx].as_deref().unwrap_or(&Page::EMPTY)
+    }
+}
+
+/// `PAGE_BITS` bits, a bit of `summary` is set iff the corresponding word is not zero
+#[derive(Debug, Clone)]
+struct Page {
+    summary: u64,
+    words: [u64; WORD_BITS],
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Page::EMPTY
+    }
+}
+
+impl Page {
+    const EMPTY: Page = Page {
+        summary: 0,
+        words: [0; WORD_BITS],
+    };
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.summary == 0
+    }
+
+    #[inline(always)]
+    fn contains(&self, bit: usize) -> bool {
+        self.words[bit / WORD_BITS] & (1 << (bit % WORD_BITS)) != 0
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, bit: usize) {
+        self.words[bit / WORD_BITS] |= 1 << (bit % WORD_BITS);
+        self.summary |= 1 << (bit / WORD_BITS);
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, bit: usize) {
+        let word = &mut self.words[bit / WORD_BITS];
+        *word &= !(1 << (bit % WORD_BITS));
+
+        if *word == 0 {
+            self.summary &= !(1 << (bit / WORD_BITS));
+        }
+    }
+
+    /// Drops the bits from `len` onwards
+    fn truncate(&mut self, len: usize) {
+        for (word_idx, word) in self.words.iter_mut().enumerate() {
+            let begin = word_idx * WORD_BITS;
+
+            if begin >= len {
+                *word = 0;
+            } else if len - begin < WORD_BITS {
+                *word &= (1 << (len - begin)) - 1;
+            }
+
+            if *word == 0 {
+                self.summary &= !(1 << word_idx);
+            }
+        }
+    }
+
+    /// Smallest set bit greater or equal to `start`
+    #[inline(always)]
+    fn next_from(&self, start: usize) -> Option<usize> {
+        let word_idx = start / WORD_BITS;
+        let word = self.words[word_idx] & (!0 << (start % WORD_BITS));
+
+        if word != 0 {
+            return Some(word_idx * WORD_BITS + word.trailing_zeros() as usize);
+        }
+
+        let summary = self.summary & (!0_u64).checked_shl(word_idx as u32 + 1).unwrap_or(0);
+
+        if summary == 0 {
+            return None;
+        }
+
+        let word_idx = summary.trailing_zeros() as usize;
+        Some(word_idx * WORD_BITS + self.words[word_idx].trailing_zeros() as usize)
+    }
+
+    /// Largest set bit less or equal to `end`
+    #[inline(always)]
+    fn prev_from(&self, end: usize) -> Option<usize> {
+        let word_idx = end / WORD_BITS;
+        let word = self.words[word_idx] & (!0 >> (WORD_BITS - 1 - end % WORD_BITS));
+
+        if word != 0 {
+            return Some(word_idx * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize));
+        }
+
+        let summary = self.summary & ((1 << word_idx) - 1);
+
+        if summary == 0 {
+            return None;
+        }
+
+        let word_idx = WORD_BITS - 1 - summary.leading_zeros() as usize;
+        let word = self.words[word_idx];
+        Some(word_idx * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize))
+    }
+}
+
+/// Hierarchical 64-ary bitset of occupied indices.
+///
+/// `layers[0]` has a bit per index, a bit of `layers[k + 1]` is set iff the corresponding word of `layers[k]` is not zero.
+/// Next/previous occupied index is found with a `trailing_zeros`/`leading_zeros` per layer going up and then down,
+/// so the lookup doesn't depend on the distance between the indices.
+#[derive(Debug, Clone, Default)]
+struct Layers {
+    layers: Vec<Vec<u64>>,
+    len: usize,
+}
+
+impl Layers {
+    pub fn new(len: usize) -> Self {
+        let mut index = Layers::default();
+        index.resize(len);
+        index
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.layers
+            .last()
+            .is_none_or(|top| top.iter().all(|word| *word == 0))
+    }
+
+    /// Grows or shrinks the index, bits beyond the new length are dropped.
+    /// Growing only appends zero words, so it costs O(added words).
+    pub fn resize(&mut self, len: usize) {
+        if len < self.len {
+            self.shrink(len);
+            return;
+        }
+
+        if self.layers.is_empty() {
+            self.layers.push(Vec::new());
+        }
+
+        let mut layer_len = len;
+
+        for layer in self.layers.iter_mut() {
+            layer_len = layer_len.div_ceil(WORD_BITS);
+            layer.resize(layer_len, 0);
+        }
+
+        self.len = len;
+        self.push_layers();
+    }
+
+    fn shrink(&mut self, len: usize) {
+        let mut leaves = std::mem::take(&mut self.layers[0]);
+        leaves.truncate(len.div_ceil(WORD_BITS));
+
+        if !len.is_multiple_of(WORD_BITS) {
+            if let Some(last) = leaves.last_mut() {
+                *last &= (1 << (len % WORD_BITS)) - 1;
+            }
+        }
+
+        self.len = len;
+        self.layers.clear();
+        self.layers.push(leaves);
+        self.push_layers();
+    }
+
+    /// Adds summary layers on top until the top one is a single word
+    fn push_layers(&mut self) {
+        while self.layers.last().unwrap().len() > 1 {
+            let below = self.layers.last().unwrap();
+            let mut layer = vec![0; below.len().div_ceil(WORD_BITS)];
+
+            for (idx, word) in below.iter().enumerate() {
+                if *word != 0 {
+                    layer[idx / WORD_BITS] |= 1 << (idx % WORD_BITS);
+                }
+            }
+
+            self.layers.push(layer);
+        }
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, idx: usize) {
+        let mut pos = idx;
+
+        for layer in self.layers.iter_mut() {
+            let word = &mut layer[pos / WORD_BITS];
+            let was_empty = *word == 0;
+            *word |= 1 << (pos % WORD_BITS);
+
+            if !was_empty {
+                return;
+            }
+
+            pos /= WORD_BITS;
+        }
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, idx: usize) {
+        let mut pos = idx;
+
+        for layer in self.layers.iter_mut() {
+            let word = &mut layer[pos / WORD_BITS];
+            *word &= !(1 << (pos % WORD_BITS));
+
+            if *word != 0 {
+                return;
+            }
+
+            pos /= WORD_BITS;
+        }
+    }
+
+    /// Smallest occupied index greater than `idx`
+    #[inline(always)]
+    pub fn next(&self, idx: usize) -> Option<usize> {
+        self.next_from(idx.checked_add(1)?)
+    }
+
+    /// Largest occupied index less than `idx`
+    #[inline(always)]
+    pub fn prev(&self, idx: usize) -> Option<usize> {
+        self.prev_from(idx.checked_sub(1)?)
+    }
+
+    /// Occupied indices in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.next_from(0), |idx| self.next(*idx))
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        for layer in self.layers.iter_mut() {
+            layer.fill(0);
+        }
+    }
+
+    /// Smallest occupied index greater or equal to `start`
+    #[inline(always)]
+    fn next_from(&self, start: usize) -> Option<usize> {
+        if start >= self.len {
+            return None;
+        }
+
+        let mut pos = start;
+        let mut depth = 0;
+
+        loop {
+            let layer = self.layers.get(depth)?;
+            let word_idx = pos / WORD_BITS;
+            let word = layer.get(word_idx)? & (!0 << (pos % WORD_BITS));
+
+            if word != 0 {
+                pos = word_idx * WORD_BITS + word.trailing_zeros() as usize;
+                break;
+            }
+
+            pos = word_idx + 1;
+            depth += 1;
+        }
+
+        while depth > 0 {
+            depth -= 1;
+            pos = pos * WORD_BITS + self.layers[depth][pos].trailing_zeros() as usize;
+        }
+
+        Some(pos)
+    }
+
+    /// Largest occupied index less or equal to `end`
+    #[inline(always)]
+    fn prev_from(&self, end: usize) -> Option<usize> {
+        let mut pos = end.min(self.len.checked_sub(1)?);
+        let mut depth = 0;
+
+        loop {
+            let layer = self.layers.get(depth)?;
+            let word_idx = pos / WORD_BITS;
+            let word = layer[word_idx] & (!0 >> (WORD_BITS - 1 - pos % WORD_BITS));
+
+            if word != 0 {
+                pos = word_idx * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize);
+                break;
+            }
+
+            pos = word_idx.checked_sub(1)?;
+            depth += 1;
+        }
+
+        while depth > 0 {
+            depth -= 1;
+            let word = self.layers[depth][pos];
+            pos = pos * WORD_BITS + (WORD_BITS - 1 - word.leading_zeros() as usize);
+        }
+
+        Some(pos)
+    }
+}
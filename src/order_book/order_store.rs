@@ -0,0 +1,208 @@
+use crate::common::{
+    intrinsics::*,
+    types::{Amount, AmountAnomaly, L2Delta, L3Delta, Price, Side},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrderEntry<P, A> {
+    pub side: Side,
+    pub px: P,
+    pub amt: A,
+}
+
+/// Orders keyed by entry ID.
+///
+/// On some exchanges entry IDs are incremental, so the store is a plain vector.
+/// It is shared by both sides (and possibly by many instruments) of a venue,
+/// see `L2BookBuilder::apply_l3_deltas`.
+#[derive(Debug, Clone)]
+pub struct OrderStore<P, A> {
+    orders: Vec<Option<OrderEntry<P, A>>>,
+}
+
+impl<P: Copy, A: Copy> OrderStore<P, A> {
+    pub fn new(reserve_size: usize) -> Self {
+        OrderStore {
+            orders: Vec::with_capacity(reserve_size),
+        }
+    }
+
+    #[inline(always)]
+    pub fn insert(&mut self, id: u64, entry: OrderEntry<P, A>) {
+        let id = id as usize;
+
+        if unlikely(self.orders.len() <= id) {
+            self.orders.resize(id + 1, None);
+            log::debug!("Resize triggered: new_len=[{}]", self.orders.len());
+        }
+
+        self.orders[id] = Some(entry);
+    }
+
+    #[inline(always)]
+    pub fn get(&self, id: u64) -> Option<&OrderEntry<P, A>> {
+        self.orders.get(id as usize)?.as_ref()
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut OrderEntry<P, A>> {
+        self.orders.get_mut(id as usize)?.as_mut()
+    }
+
+    #[inline(always)]
+    pub fn remove(&mut self, id: u64) -> Option<OrderEntry<P, A>> {
+        self.orders.get_mut(id as usize)?.take()
+    }
+
+    #[inline(always)]
+    pub fn clear(&mut self) {
+        self.orders.clear();
+    }
+
+    /// Side of the order the event refers to, `None` for unknown orders.
+    #[inline(always)]
+    pub fn side_of(&self, l3_delta: &L3Delta<P, A>) -> Option<Side> {
+        match *l3_delta {
+            L3Delta::Add { side, .. } => Some(side),
+            L3Delta::Reduce { id, .. } | L3Delta::Delete { id } | L3Delta::Replace { id, .. } => {
+                self.get(id).map(|order| order.side)
+            }
+        }
+    }
+}
+
+impl<P: Price, A: Amount> OrderStore<P, A> {
+    /// Applies the event to the stored orders and reports the resulting `L2Delta`s.
+    /// Events for unknown orders are ignored, overfills are logged.
+    #[inline(always)]
+    pub fn apply(
+        &mut self,
+        l3_delta: &L3Delta<P, A>,
+        process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+    ) {
+        self.apply_with(l3_delta, process_l2_delta, AmountAnomaly::log);
+    }
+
+    /// A reduce larger than the order removes it and is reported through `on_anomaly`
+    #[inline(always)]
+    pub fn apply_with(
+        &mut self,
+        l3_delta: &L3Delta<P, A>,
+        mut process_l2_delta: impl FnMut(&Side, &L2Delta<P, A>),
+        mut on_anomaly: impl FnMut(&AmountAnomaly<P, A>),
+    ) {
+        match *l3_delta {
+            L3Delta::Add { id, side, px, amt } => {
+                if let Some(order) = self.remove(id) {
+                    log::warn!("Duplicate order: id=[{}]", id);
+                    process_l2_delta(
+                        &order.side,
+                        &L2Delta {
+                            px: order.px,
+                            amt_delta: -order.amt.as_delta(),
+                            order_count_delta: -1,
+                        },
+                    );
+                }
+
+                self.insert(id, OrderEntry { side, px, amt });
+
+                process_l2_delta(
+                    &side,
+                    &L2Delta {
+                        px,
+                        amt_delta: amt.as_delta(),
+                        order_count_delta: 1,
+                    },
+                );
+            }
+            L3Delta::Reduce { id, amt } => {
+                let order = match self.get_mut(id) {
+                    Some(o) => o,
+                    None => return,
+                };
+
+                let OrderEntry { side, px, .. } = *order;
+                let (reduced, order_count_delta) = if amt >= order.amt {
+                    let reduced = order.amt;
+
+                    if unlikely(amt > reduced) {
+                        on_anomaly(&AmountAnomaly {
+                            side,
+                            px,
+                            prev_amt: reduced,
+                            amt_delta: -amt.as_delta(),
+                        });
+                    }
+
+                    self.remove(id);
+                    (reduced, -1)
+                } else {
+                    order.amt = order.amt - amt;
+                    (amt, 0)
+                };
+
+                process_l2_delta(
+                    &side,
+                    &L2Delta {
+                        px,
+                        amt_delta: -reduced.as_delta(),
+                        order_count_delta,
+                    },
+                );
+            }
+            L3Delta::Delete { id } => {
+                let order = match self.remove(id) {
+                    Some(o) => o,
+                    None => return,
+                };
+
+                process_l2_delta(
+                    &order.side,
+                    &L2Delta {
+                        px: order.px,
+                        amt_delta: -order.amt.as_delta(),
+                        order_count_delta: -1,
+                    },
+                );
+            }
+            L3Delta::Replace {
+                id,
+                new_id,
+                px,
+                amt,
+            } => {
+                let order = match self.remove(id) {
+                    Some(o) => o,
+                    None => return,
+                };
+
+                self.insert(
+                    new_id,
+                    OrderEntry {
+                        side: order.side,
+                        px,
+                        amt,
+                    },
+                );
+
+                process_l2_delta(
+                    &order.side,
+                    &L2Delta {
+                        px: order.px,
+                        amt_delta: -order.amt.as_delta(),
+                        order_count_delta: -1,
+                    },
+                );
+                process_l2_delta(
+                    &order.side,
+                    &L2Delta {
+                        px,
+                        amt_delta: amt.as_delta(),
+                        order_count_delta: 1,
+                    },
+                );
+            }
+        }
+    }
+}
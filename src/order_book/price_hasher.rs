@@ -1,65 +1,114 @@
-use crate::common::{intrinsics::*, types::Price};
+use crate::common::{intrinsics::*, types::Price, TickSchedule};
 
+/// How far below a new low price the range is extended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeGrowth {
+    Ticks(u64),
+    /// Of the distance from zero in ticks (i.e. `px * (1 - pct / 100)` for positive prices),
+    /// but at least a few ticks, so prices around zero don't shift on every new low
+    Percent(u64),
+}
+
+impl Default for RangeGrowth {
+    fn default() -> Self {
+        RangeGrowth::Percent(10)
+    }
+}
+
+/// Maps prices to indices from the start of the range.
+///
+/// Tick indices are signed, the range may start below zero and span it.
 #[derive(Debug, Clone, Copy)]
 pub struct PriceHasher<P> {
-    px_min: P,
-    tick_size: P,
+    tick_idx_min: i64,
+    tick_schedule: TickSchedule<P>,
+    growth: RangeGrowth,
 }
 
 impl<P: Price> PriceHasher<P> {
-    const GROWTH_FACTOR: f64 = 0.1;
+    const MIN_GROWTH_TICKS: u64 = 64;
 
-    pub fn new(start_px: P, tick_size: P) -> Self {
+    pub fn new(start_px: P, tick_schedule: TickSchedule<P>) -> Self {
         PriceHasher {
-            px_min: start_px,
-            tick_size,
+            tick_idx_min: tick_schedule.px_to_tick_idx(&start_px),
+            tick_schedule,
+            growth: RangeGrowth::default(),
         }
     }
 
+    pub fn with_growth(self, growth: RangeGrowth) -> Self {
+        PriceHasher { growth, ..self }
+    }
+
     #[inline(always)]
     pub fn hash(&mut self, px: &P) -> (usize, usize) {
         let tick_idx = self.px_to_tick_idx(px);
-        let tick_idx_min = self.px_to_tick_idx(&self.px_min);
+        let tick_idx_min = self.tick_idx_min;
 
         // Case 1: px_min <= px <= px_max
         // Just extract the index, range remains the same, no need to shift
         if likely(tick_idx >= tick_idx_min) {
-            return (tick_idx - tick_idx_min, 0);
+            return ((tick_idx - tick_idx_min) as usize, 0);
         }
 
         // Case 2: px < px_min
-        // Range becomes [px_min * scale, px_max] and we have to shift to the right
-        let new_px_min = self.round_to_tick_size(*px * (1.0 - Self::GROWTH_FACTOR));
-        let new_tick_idx_min = self.px_to_tick_idx(&new_px_min);
-        self.px_min = new_px_min;
+        // Range becomes [px - growth, px_max] and we have to shift to the right
+        let growth = match self.growth {
+            RangeGrowth::Ticks(ticks) => ticks,
+            RangeGrowth::Percent(pct) => {
+                (tick_idx.unsigned_abs().saturating_mul(pct) / 100).max(Self::MIN_GROWTH_TICKS)
+            }
+        };
+        let new_tick_idx_min = tick_idx.saturating_sub_unsigned(growth);
+        self.tick_idx_min = new_tick_idx_min;
 
-        (tick_idx - new_tick_idx_min, tick_idx_min - new_tick_idx_min)
+        (
+            (tick_idx - new_tick_idx_min) as usize,
+            (tick_idx_min - new_tick_idx_min) as usize,
+        )
     }
 
     #[inline(always)]
     pub fn try_hash(&self, px: &P) -> Option<usize> {
         let tick_idx = self.px_to_tick_idx(px);
-        let tick_idx_min = self.px_to_tick_idx(&self.px_min);
 
-        if likely(tick_idx >= tick_idx_min) {
-            return Some(tick_idx - tick_idx_min);
+        if likely(tick_idx >= self.tick_idx_min) {
+            return Some((tick_idx - self.tick_idx_min) as usize);
         }
 
         None
     }
 
+    /// Absolute tick index of the price, regardless of the current range
     #[inline(always)]
-    pub fn idx_to_px(&self, idx: &usize) -> P {
-        P::tick_idx_to_px(&(idx + self.px_to_tick_idx(&self.px_min)), &self.tick_size)
+    pub fn tick_idx(&self, px: &P) -> i64 {
+        self.px_to_tick_idx(px)
     }
 
     #[inline(always)]
-    fn px_to_tick_idx(&self, px: &P) -> usize {
-        P::px_to_tick_idx(&px, &self.tick_size)
+    pub fn tick_idx_min(&self) -> i64 {
+        self.tick_idx_min
+    }
+
+    #[inline(always)]
+    pub fn tick_idx_to_px(&self, tick_idx: &i64) -> P {
+        self.tick_schedule.tick_idx_to_px(tick_idx)
+    }
+
+    /// Moves the start of the range to the given absolute tick index
+    #[inline(always)]
+    pub fn rebase(&mut self, tick_idx_min: i64) {
+        self.tick_idx_min = tick_idx_min;
+    }
+
+    #[inline(always)]
+    pub fn idx_to_px(&self, idx: &usize) -> P {
+        self.tick_schedule
+            .tick_idx_to_px(&(*idx as i64 + self.tick_idx_min))
     }
 
     #[inline(always)]
-    fn round_to_tick_size(&self, val: P) -> P {
-        P::round_to_tick_size(&val, &self.tick_size)
+    fn px_to_tick_idx(&self, px: &P) -> i64 {
+        self.tick_schedule.px_to_tick_idx(px)
     }
 }
@@ -1,134 +1,484 @@
-use super::price_hasher::PriceHasher;
+use super::level_storage::{DenseLevels, LevelStorage};
+use super::occupancy_index::OccupancyIndex;
+use super::price_hasher::{PriceHasher, RangeGrowth};
 use crate::common::{
     intrinsics::*,
     types::{Amount, Level, Price},
+    TickSchedule,
 };
 
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct PriceLevel<A> {
     pub amt: A,
+    /// Maintained from order-level events or venue-published counts, zero otherwise
+    pub order_count: u32,
 }
 
-#[derive(Debug, Clone)]
-pub struct PriceMap<P: Price, A: Amount> {
-    px_hasher: PriceHasher<P>,
-    levels: Vec<PriceLevel<A>>,
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Window(usize),
+    Overflow(i64),
 }
 
-impl<P: Price, A: Amount> PriceMap<P, A> {
-    pub fn new(start_px: P, _end_px: Option<P>, tick_size: P) -> Self {
-        PriceMap {
-            px_hasher: PriceHasher::new(start_px, tick_size),
-            levels: Vec::new(),
-        }
+/// Mutable access to a level, the level is written back (and the occupancy index is updated)
+/// once the access is dropped.
+pub struct PriceLevelMut<'a, P: Price, A: Amount, S: LevelStorage<A> = DenseLevels<A>> {
+    map: &'a mut PriceMap<P, A, S>,
+    slot: Slot,
+    level: PriceLevel<A>,
+}
+
+impl<P: Price, A: Amount, S: LevelStorage<A>> Deref for PriceLevelMut<'_, P, A, S> {
+    type Target = PriceLevel<A>;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.level
     }
+}
 
-    /// May trigger rehashing
+impl<P: Price, A: Amount, S: LevelStorage<A>> DerefMut for PriceLevelMut<'_, P, A, S> {
     #[inline(always)]
-    pub fn get_mut(&mut self, px: P) -> &mut PriceLevel<A> {
-        let (px_idx, shift) = self.px_hasher.hash(&px);
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.level
+    }
+}
 
-        if unlikely(px_idx >= self.levels.len()) {
-            self.levels.resize(px_idx + 1, PriceLevel::default());
-            log::debug!("Resize triggered: new_len=[{}]", self.levels.len());
-        }
+impl<P: Price, A: Amount, S: LevelStorage<A>> Drop for PriceLevelMut<'_, P, A, S> {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let is_occupied = !self.level.amt.is_zero();
 
-        if likely(shift == 0) {
-            return &mut self.levels[px_idx];
+        match self.slot {
+            Slot::Window(idx) => {
+                let was_occupied = self.map.occupancy.contains(idx);
+
+                if is_occupied || was_occupied {
+                    *self.map.levels.get_mut(idx) = self.level;
+                }
+
+                if is_occupied != was_occupied {
+                    if is_occupied {
+                        self.map.occupancy.insert(idx);
+                    } else {
+                        self.map.occupancy.remove(idx);
+                    }
+                }
+            }
+            Slot::Overflow(tick_idx) => {
+                if is_occupied {
+                    self.map.overflow.insert(tick_idx, self.level);
+                } else {
+                    self.map.overflow.remove(&tick_idx);
+                }
+            }
         }
+    }
+}
 
-        let mut new_levels = vec![PriceLevel::default(); shift + self.levels.len()];
+/// Levels are stored in `S` (a dense vector by default) indexed by ticks from `start_px`.
+///
+/// Unbounded by default: the storage grows to whatever range the prices span,
+/// `end_px` only preallocates it.
+/// With `max_ticks` the storage is a fixed window that follows the top (see `recenter`),
+/// levels outside of it are kept in a cold overflow map.
+#[derive(Debug, Clone)]
+pub struct PriceMap<P: Price, A: Amount, S: LevelStorage<A> = DenseLevels<A>> {
+    px_hasher: PriceHasher<P>,
+    levels: S,
+    occupancy: OccupancyIndex,
+    max_ticks: Option<usize>,
+    overflow: BTreeMap<i64, PriceLevel<A>>,
+}
+
+impl<P: Price, A: Amount, S: LevelStorage<A>> PriceMap<P, A, S> {
+    pub fn new(
+        start_px: P,
+        end_px: Option<P>,
+        tick_schedule: impl Into<TickSchedule<P>>,
+        max_ticks: Option<usize>,
+    ) -> Self {
+        let px_hasher = PriceHasher::new(start_px, tick_schedule.into());
 
-        for (i, level) in self.levels.iter().enumerate() {
-            new_levels[shift + i] = *level;
+        let len = match (max_ticks, end_px) {
+            (Some(max_ticks), _) => max_ticks,
+            (None, Some(end_px)) => px_hasher.try_hash(&end_px).map_or(0, |idx| idx + 1),
+            (None, None) => 0,
+        };
+
+        PriceMap {
+            px_hasher,
+            levels: S::with_len(len),
+            occupancy: OccupancyIndex::new(len),
+            max_ticks,
+            overflow: BTreeMap::new(),
         }
+    }
 
-        self.levels = new_levels;
-        log::debug!("Shift triggered: new_len=[{}]", self.levels.len());
+    /// How the unbounded range grows on a new low price
+    pub fn with_growth(self, growth: RangeGrowth) -> Self {
+        PriceMap {
+            px_hasher: self.px_hasher.with_growth(growth),
+            ..self
+        }
+    }
 
-        &mut self.levels[px_idx]
+    /// May trigger rehashing when unbounded
+    #[inline(always)]
+    pub fn get_mut(&mut self, px: P) -> PriceLevelMut<'_, P, A, S> {
+        let slot = match self.max_ticks {
+            None => Slot::Window(self.grow_to(px)),
+            Some(_) => match self.px_hasher.try_hash(&px) {
+                Some(px_idx) if likely(px_idx < self.levels.len()) => Slot::Window(px_idx),
+                _ => Slot::Overflow(self.px_hasher.tick_idx(&px)),
+            },
+        };
+
+        let level = match slot {
+            Slot::Window(idx) => self.levels.get(idx),
+            Slot::Overflow(tick_idx) => self.overflow.get(&tick_idx).copied().unwrap_or_default(),
+        };
+
+        PriceLevelMut {
+            map: self,
+            slot,
+            level,
+        }
     }
 
     /// May not trigger rehashing
     #[inline(always)]
     pub fn get_immut(&self, px: P) -> PriceLevel<A> {
         match self.px_hasher.try_hash(&px) {
-            Some(px_idx) => self.levels[px_idx],
-            None => PriceLevel::default(),
+            Some(px_idx) if px_idx < self.levels.len() => self.levels.get(px_idx),
+            _ => self
+                .overflow
+                .get(&self.px_hasher.tick_idx(&px))
+                .copied()
+                .unwrap_or_default(),
         }
     }
 
     #[inline(always)]
     pub fn next_px<const REVERSE: bool>(&self, px: &P) -> Option<Level<P, A>> {
-        let px_idx = match self.px_hasher.try_hash(&px) {
-            Some(px_idx) => px_idx,
-            None => return None,
-        };
+        self.next_level::<REVERSE>(self.px_hasher.tick_idx(px))
+            .map(|(_, level)| level)
+    }
+
+    /// Non-empty levels in price order: descending for `REVERSE`, ascending otherwise
+    #[inline(always)]
+    pub fn iter<const REVERSE: bool>(&self) -> LevelIter<'_, P, A, S, REVERSE> {
+        LevelIter {
+            map: self,
+            tick_idx: if REVERSE { i64::MAX } else { i64::MIN },
+        }
+    }
+
+    /// Absolute tick index of the price
+    #[inline(always)]
+    pub fn tick_idx(&self, px: &P) -> i64 {
+        self.px_hasher.tick_idx(px)
+    }
+
+    /// The first non-empty level after `tick_idx` (exclusive) in the direction of `REVERSE`
+    #[inline(always)]
+    fn next_level<const REVERSE: bool>(&self, tick_idx: i64) -> Option<(i64, Level<P, A>)> {
+        let (window_begin, window_end) = self.window();
 
         if REVERSE {
-            for (idx, level) in self
-                .levels
-                .iter()
-                .enumerate()
-                .rev()
-                .skip(self.levels.len() - px_idx)
-            {
-                if likely(!level.amt.is_zero()) {
-                    return Some(Level {
-                        px: self.px_hasher.idx_to_px(&idx),
-                        amt: level.amt,
-                    });
+            if unlikely(tick_idx > window_end) {
+                if let Some(level) =
+                    self.overflow_level(self.overflow.range(window_end..tick_idx).next_back())
+                {
+                    return Some(level);
                 }
             }
+
+            let idx = if tick_idx >= window_end {
+                self.occupancy.last()
+            } else if tick_idx > window_begin {
+                self.occupancy.prev((tick_idx - window_begin) as usize)
+            } else {
+                None
+            };
+
+            match idx {
+                Some(idx) => Some(self.level_at(idx)),
+                None => self.overflow_level(
+                    self.overflow
+                        .range(..tick_idx.min(window_begin))
+                        .next_back(),
+                ),
+            }
         } else {
-            for (idx, level) in self.levels.iter().enumerate().skip(px_idx + 1) {
-                if likely(!level.amt.is_zero()) {
-                    return Some(Level {
-                        px: self.px_hasher.idx_to_px(&idx),
-                        amt: level.amt,
-                    });
+            if unlikely(tick_idx + 1 < window_begin) {
+                if let Some(level) =
+                    self.overflow_level(self.overflow.range(tick_idx + 1..window_begin).next())
+                {
+                    return Some(level);
                 }
             }
+
+            let idx = if tick_idx < window_begin {
+                self.occupancy.first()
+            } else {
+                self.occupancy.next((tick_idx - window_begin) as usize)
+            };
+
+            match idx {
+                Some(idx) => Some(self.level_at(idx)),
+                None => self
+                    .overflow_level(self.overflow.range((tick_idx + 1).max(window_end)..).next()),
+            }
+        }
+    }
+
+    /// Re-centers the window around `top_px` once the top leaves the middle half of it,
+    /// levels left outside of the window are spilled to the overflow map and reported.
+    /// The storage is moved in place, so the cost is of the spilled and restored levels
+    /// (plus a copy of the window for `DenseLevels`). No-op when unbounded.
+    #[inline(always)]
+    pub fn recenter(&mut self, top_px: P) {
+        let max_ticks = match self.max_ticks {
+            Some(max_ticks) => max_ticks,
+            None => return,
+        };
+
+        let tick_idx = self.px_hasher.tick_idx(&top_px);
+        let (window_begin, window_end) = self.window();
+        let margin = (max_ticks / 4) as i64;
+
+        if likely(tick_idx >= window_begin + margin && tick_idx < window_end - margin) {
+            return;
+        }
+
+        let new_begin = tick_idx - (max_ticks / 2) as i64;
+        let new_end = new_begin + max_ticks as i64;
+
+        let keep_begin = (new_begin.clamp(window_begin, window_end) - window_begin) as usize;
+        let keep_end = (new_end.clamp(window_begin, window_end) - window_begin) as usize;
+        let spilled = self.spill(0, keep_begin) + self.spill(keep_end, max_ticks);
+
+        // Disjoint windows are empty by now, only the start moves
+        if keep_begin < keep_end {
+            if new_begin < window_begin {
+                let shift = (window_begin - new_begin) as usize;
+                self.levels.shift(shift);
+                self.occupancy.shift(shift);
+                self.levels.truncate(max_ticks);
+                self.occupancy.resize(max_ticks);
+            } else {
+                let count = (new_begin - window_begin) as usize;
+                self.levels.drop_front(count);
+                self.occupancy.drop_front(count);
+                self.levels.grow(max_ticks);
+                self.occupancy.resize(max_ticks);
+            }
         }
+        self.px_hasher.rebase(new_begin);
+
+        let mut restored = self.overflow.split_off(&new_begin);
+        let mut above = restored.split_off(&new_end);
+        self.overflow.append(&mut above);
+
+        for (level_tick_idx, level) in restored.iter() {
+            let idx = (level_tick_idx - new_begin) as usize;
+            *self.levels.get_mut(idx) = *level;
+            self.occupancy.insert(idx);
+        }
+
+        log::warn!(
+            "Window recentered: top_px=[{:?}], spilled=[{}], restored=[{}], overflow_len=[{}]",
+            top_px,
+            spilled,
+            restored.len(),
+            self.overflow.len()
+        );
+    }
+
+    /// Moves the window levels in `[begin, end)` to the overflow map, returns their number
+    fn spill(&mut self, begin: usize, end: usize) -> usize {
+        let (window_begin, _) = self.window();
+        let mut idx = match begin {
+            0 => self.occupancy.first(),
+            _ => self.occupancy.next(begin - 1),
+        };
+        let mut spilled = 0;
+
+        while let Some(level_idx) = idx.filter(|idx| *idx < end) {
+            self.overflow
+                .insert(window_begin + level_idx as i64, self.levels.get(level_idx));
+            *self.levels.get_mut(level_idx) = PriceLevel::default();
+            self.occupancy.remove(level_idx);
+            spilled += 1;
+
+            idx = self.occupancy.next(level_idx);
+        }
+
+        spilled
+    }
 
-        None
+    /// Number of levels kept outside of the window
+    #[inline(always)]
+    pub fn overflow_len(&self) -> usize {
+        self.overflow.len()
     }
 
     #[inline(always)]
     pub fn clear(&mut self) {
-        self.levels.clear();
+        for idx in self.occupancy.iter() {
+            *self.levels.get_mut(idx) = PriceLevel::default();
+        }
+
+        self.occupancy.clear();
+        self.overflow.clear();
     }
-}
 
-impl<'a, P: Price, A: Amount + 'a> PriceMap<P, A> {
     pub fn top_levels<const N: usize, const REVERSE: bool>(
         &self,
     ) -> [Option<(P, PriceLevel<A>)>; N] {
+        let mut top = [None; N];
+        let mut cur_idx = 0;
+
+        self.for_each_top::<REVERSE>(N, |px, level| {
+            top[cur_idx] = Some((px, level));
+            cur_idx += 1;
+        });
+
+        top
+    }
+
+    /// Same as `top_levels` for a depth known at runtime, `top` is cleared first
+    pub fn top_levels_into<const REVERSE: bool>(
+        &self,
+        depth: usize,
+        top: &mut Vec<(P, PriceLevel<A>)>,
+    ) {
+        top.clear();
+        self.for_each_top::<REVERSE>(depth, |px, level| top.push((px, level)));
+    }
+
+    fn for_each_top<const REVERSE: bool>(&self, depth: usize, f: impl FnMut(P, PriceLevel<A>)) {
+        let (window_begin, window_end) = self.window();
+        let below = self.overflow.range(..window_begin);
+        let above = self.overflow.range(window_end..);
+
         if REVERSE {
-            self.collect_top(self.levels.iter().enumerate().rev())
+            self.visit_top(
+                above
+                    .rev()
+                    .map(|(tick_idx, level)| (*tick_idx, *level))
+                    .chain(self.window_levels(self.occupancy.iter_rev()))
+                    .chain(below.rev().map(|(tick_idx, level)| (*tick_idx, *level))),
+                depth,
+                f,
+            )
         } else {
-            self.collect_top(self.levels.iter().enumerate())
+            self.visit_top(
+                below
+                    .map(|(tick_idx, level)| (*tick_idx, *level))
+                    .chain(self.window_levels(self.occupancy.iter()))
+                    .chain(above.map(|(tick_idx, level)| (*tick_idx, *level))),
+                depth,
+                f,
+            )
         }
     }
 
-    fn collect_top<const N: usize, Iter>(&self, iter: Iter) -> [Option<(P, PriceLevel<A>)>; N]
-    where
-        Iter: Iterator<Item = (usize, &'a PriceLevel<A>)>,
-    {
-        let mut top = [None; N];
-        let mut cur_idx = 0;
+    fn visit_top(
+        &self,
+        iter: impl Iterator<Item = (i64, PriceLevel<A>)>,
+        depth: usize,
+        mut f: impl FnMut(P, PriceLevel<A>),
+    ) {
+        for (tick_idx, level) in iter.take(depth) {
+            f(self.px_hasher.tick_idx_to_px(&tick_idx), level);
+        }
+    }
 
-        for (idx, level) in iter {
-            if !level.amt.is_zero() {
-                top[cur_idx] = Some((self.px_hasher.idx_to_px(&idx), *level));
+    fn window_levels<'a>(
+        &'a self,
+        iter: impl Iterator<Item = usize> + 'a,
+    ) -> impl Iterator<Item = (i64, PriceLevel<A>)> + 'a {
+        let tick_idx_min = self.px_hasher.tick_idx_min();
+        iter.map(move |idx| (tick_idx_min + idx as i64, self.levels.get(idx)))
+    }
 
-                cur_idx += 1;
-                if cur_idx == N {
-                    break;
-                }
-            }
+    /// Absolute tick indices covered by `levels`, `[begin, end)`
+    #[inline(always)]
+    fn window(&self) -> (i64, i64) {
+        let tick_idx_min = self.px_hasher.tick_idx_min();
+        (tick_idx_min, tick_idx_min + self.levels.len() as i64)
+    }
+
+    /// Unbounded mode: grows the range to contain the price
+    #[inline(always)]
+    fn grow_to(&mut self, px: P) -> usize {
+        let (px_idx, shift) = self.px_hasher.hash(&px);
+
+        if unlikely(px_idx >= self.levels.len()) {
+            self.levels.grow(px_idx + 1);
+            self.occupancy.resize(self.levels.len());
+            log::debug!("Resize triggered: new_len=[{}]", self.levels.len());
         }
 
-        top
+        if unlikely(shift != 0) {
+            self.levels.shift(shift);
+            self.occupancy.shift(shift);
+            log::debug!("Shift triggered: new_len=[{}]", self.levels.len());
+        }
+
+        px_idx
+    }
+
+    #[inline(always)]
+    fn level_at(&self, idx: usize) -> (i64, Level<P, A>) {
+        let level = self.levels.get(idx);
+
+        (
+            self.px_hasher.tick_idx_min() + idx as i64,
+            Level {
+                px: self.px_hasher.idx_to_px(&idx),
+                amt: level.amt,
+                order_count: level.order_count,
+            },
+        )
+    }
+
+    #[inline(always)]
+    fn overflow_level(&self, entry: Option<(&i64, &PriceLevel<A>)>) -> Option<(i64, Level<P, A>)> {
+        let (tick_idx, level) = entry?;
+
+        Some((
+            *tick_idx,
+            Level {
+                px: self.px_hasher.tick_idx_to_px(tick_idx),
+                amt: level.amt,
+                order_count: level.order_count,
+            },
+        ))
+    }
+}
+
+/// See `PriceMap::iter`
+pub struct LevelIter<'a, P: Price, A: Amount, S: LevelStorage<A>, const REVERSE: bool> {
+    map: &'a PriceMap<P, A, S>,
+    tick_idx: i64,
+}
+
+impl<P: Price, A: Amount, S: LevelStorage<A>, const REVERSE: bool> Iterator
+    for LevelIter<'_, P, A, S, REVERSE>
+{
+    type Item = Level<P, A>;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (tick_idx, level) = self.map.next_level::<REVERSE>(self.tick_idx)?;
+        self.tick_idx = tick_idx;
+
+        Some(level)
     }
 }
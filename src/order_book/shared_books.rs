@@ -0,0 +1,291 @@
+use super::{LevelObserver, LevelStorage, OrderBook, TopLevels, TopOfBook};
+use crate::common::types::{Amount, Level, Price};
+use crate::common::{Decimal, SeqLock};
+
+use memmap2::{Mmap, MmapMut};
+
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::marker::PhantomData;
+use std::mem::{align_of, size_of};
+use std::path::Path;
+use std::sync::atomic::{fence, Ordering};
+
+const MAGIC: u64 = u64::from_le_bytes(*b"LOBOTOMY");
+const VERSION: u32 = 2;
+
+/// Type tag of prices and amounts in the header of a shared books file,
+/// types of the same size (e.g. `f64` and `i64`) are told apart by it.
+pub trait SharedValue {
+    const KIND: u32;
+    /// Decimals of fixed-point types, zero otherwise
+    const SCALE: u32 = 0;
+}
+
+impl SharedValue for f64 {
+    const KIND: u32 = 1;
+}
+
+impl SharedValue for i64 {
+    const KIND: u32 = 2;
+}
+
+impl SharedValue for u64 {
+    const KIND: u32 = 3;
+}
+
+impl SharedValue for u32 {
+    const KIND: u32 = 4;
+}
+
+impl<const SCALE: u32> SharedValue for Decimal<SCALE> {
+    const KIND: u32 = 5;
+    const SCALE: u32 = SCALE;
+}
+
+/// First 64 bytes of the file
+#[repr(C, align(64))]
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    magic: u64,
+    version: u32,
+    /// `N` of the slots
+    depth: u32,
+    instrument_count: u32,
+    /// Sizes in bytes, guard against mismatched `P` and `A`
+    slot_size: u32,
+    level_size: u32,
+    /// See `SharedValue`
+    px_kind: u32,
+    px_scale: u32,
+    amt_kind: u32,
+    amt_scale: u32,
+}
+
+type Slot<P, A, const N: usize> = SeqLock<TopOfBook<P, A, N>>;
+
+/// Publishes the top `N` levels of many instruments into a memory-mapped file,
+/// to be read by other processes with `SharedBooksReader`.
+///
+/// Layout, native endianness, all `repr(C)`:
+///
+/// - `[0, 64)`: header: magic `b"LOBOTOMY"`, version `u32`, depth `u32`, instrument count `u32`, slot size `u32`, level size `u32`,
+///   then `SharedValue` kind and scale `u32`s of the price and of the amount
+/// - `[64 + i * slot_size, 64 + (i + 1) * slot_size)`: instrument `i`, a `SeqLock<TopOfBook<P, A, N>>`:
+///   the sequence `u64` (odd while being written, twice the number of updates otherwise),
+///   then bids and asks as `[Level<P, A>; N]` and their lengths as `usize`
+///
+/// Both sides have to agree on `P`, `A` and `N`, which is checked through the header.
+#[derive(Debug)]
+pub struct SharedBooksWriter<P, A, const N: usize> {
+    /// Keeps the mapping alive, slots are written through `base`
+    _mmap: MmapMut,
+    base: *mut u8,
+    instrument_count: usize,
+    _marker: PhantomData<(P, A)>,
+}
+
+/// The mapping is owned and slots are only written through `SeqLock`
+unsafe impl<P: Send, A: Send, const N: usize> Send for SharedBooksWriter<P, A, N> {}
+unsafe impl<P: Send, A: Send, const N: usize> Sync for SharedBooksWriter<P, A, N> {}
+
+impl<P: Price + SharedValue, A: Amount + SharedValue, const N: usize> SharedBooksWriter<P, A, N> {
+    /// Creates or replaces the file, all books start empty.
+    /// The new file is written aside and renamed into place, so readers of a replaced file
+    /// keep their (stale) mapping instead of faulting on a truncated one.
+    pub fn create(path: impl AsRef<Path>, instrument_count: usize) -> io::Result<Self> {
+        let path = path.as_ref();
+        let mut tmp_path = OsString::from(path);
+        tmp_path.push(".tmp");
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+        file.set_len(file_len::<P, A, N>(instrument_count) as u64)?;
+
+        let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+        let base = mmap.as_mut_ptr();
+        unsafe {
+            for idx in 0..instrument_count {
+                base.add(slot_offset::<P, A, N>(idx))
+                    .cast::<Slot<P, A, N>>()
+                    .write(SeqLock::new(TopOfBook::default()));
+            }
+
+            // Readers validate the header, so it goes last
+            fence(Ordering::Release);
+            base.cast::<Header>().write(Header {
+                magic: MAGIC,
+                version: VERSION,
+                depth: N as u32,
+                instrument_count: instrument_count as u32,
+                slot_size: size_of::<Slot<P, A, N>>() as u32,
+                level_size: size_of::<Level<P, A>>() as u32,
+                px_kind: P::KIND,
+                px_scale: P::SCALE,
+                amt_kind: A::KIND,
+                amt_scale: A::SCALE,
+            });
+        }
+        std::fs::rename(&tmp_path, path)?;
+
+        Ok(SharedBooksWriter {
+            _mmap: mmap,
+            base,
+            instrument_count,
+            _marker: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn instrument_count(&self) -> usize {
+        self.instrument_count
+    }
+
+    #[inline(always)]
+    pub fn publish(&self, instrument: usize, top: &TopOfBook<P, A, N>) {
+        self.slot(instrument).write(top);
+    }
+
+    #[inline(always)]
+    pub fn publish_book<const D: usize, S, T, O>(
+        &self,
+        instrument: usize,
+        book: &OrderBook<P, A, D, S, T, O>,
+    ) where
+        S: LevelStorage<A>,
+        T: TopLevels<P, A, D>,
+        O: LevelObserver<P, A>,
+    {
+        book.publish_top(self.slot(instrument));
+    }
+
+    #[inline(always)]
+    fn slot(&self, instrument: usize) -> &Slot<P, A, N> {
+        assert!(
+            instrument < self.instrument_count,
+            "Unknown instrument: instrument=[{}], instrument_count=[{}]",
+            instrument,
+            self.instrument_count
+        );
+
+        unsafe { slot_at(self.base, instrument) }
+    }
+}
+
+/// Attaches to the books of a `SharedBooksWriter`, e.g. from a monitoring process.
+/// Reads never block the writer, see `SeqLock`.
+#[derive(Debug)]
+pub struct SharedBooksReader<P, A, const N: usize> {
+    mmap: Mmap,
+    instrument_count: usize,
+    _marker: PhantomData<(P, A)>,
+}
+
+impl<P: Price + SharedValue, A: Amount + SharedValue, const N: usize> SharedBooksReader<P, A, N> {
+    /// Fails with `InvalidData` if the file was not written for the same `P`, `A` and `N`
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < size_of::<Header>() {
+            return Err(invalid_data("File is too short for the header"));
+        }
+
+        let header = unsafe { mmap.as_ptr().cast::<Header>().read() };
+        fence(Ordering::Acquire);
+
+        if header.magic != MAGIC || header.version != VERSION {
+            return Err(invalid_data("Not a shared books file"));
+        }
+        if header.depth as usize != N
+            || header.slot_size as usize != size_of::<Slot<P, A, N>>()
+            || header.level_size as usize != size_of::<Level<P, A>>()
+        {
+            return Err(invalid_data("Depth or level types don't match"));
+        }
+        if (header.px_kind, header.px_scale) != (P::KIND, P::SCALE)
+            || (header.amt_kind, header.amt_scale) != (A::KIND, A::SCALE)
+        {
+            return Err(invalid_data("Price or amount types don't match"));
+        }
+
+        let instrument_count = header.instrument_count as usize;
+        if mmap.len() < file_len::<P, A, N>(instrument_count) {
+            return Err(invalid_data("File is too short for the books"));
+        }
+
+        Ok(SharedBooksReader {
+            mmap,
+            instrument_count,
+            _marker: PhantomData,
+        })
+    }
+
+    #[inline(always)]
+    pub fn instrument_count(&self) -> usize {
+        self.instrument_count
+    }
+
+    /// Spins while the instrument is being written
+    #[inline(always)]
+    pub fn read(&self, instrument: usize) -> TopOfBook<P, A, N> {
+        self.slot(instrument).read()
+    }
+
+    #[inline(always)]
+    pub fn try_read(&self, instrument: usize) -> Option<TopOfBook<P, A, N>> {
+        self.slot(instrument).try_read()
+    }
+
+    /// Changes whenever the instrument is published, see `SeqLock::seq`
+    #[inline(always)]
+    pub fn seq(&self, instrument: usize) -> u64 {
+        self.slot(instrument).seq()
+    }
+
+    #[inline(always)]
+    fn slot(&self, instrument: usize) -> &Slot<P, A, N> {
+        assert!(
+            instrument < self.instrument_count,
+            "Unknown instrument: instrument=[{}], instrument_count=[{}]",
+            instrument,
+            self.instrument_count
+        );
+
+        unsafe { slot_at(self.mmap.as_ptr(), instrument) }
+    }
+}
+
+#[inline(always)]
+fn slot_offset<P, A, const N: usize>(instrument: usize) -> usize {
+    size_of::<Header>() + instrument * size_of::<Slot<P, A, N>>()
+}
+
+#[inline(always)]
+fn file_len<P, A, const N: usize>(instrument_count: usize) -> usize {
+    slot_offset::<P, A, N>(instrument_count)
+}
+
+/// Mappings are page-aligned and slots are multiples of their alignment,
+/// so every slot is properly aligned.
+#[inline(always)]
+unsafe fn slot_at<'a, P, A, const N: usize>(
+    base: *const u8,
+    instrument: usize,
+) -> &'a Slot<P, A, N> {
+    let slot = base
+        .add(slot_offset::<P, A, N>(instrument))
+        .cast::<Slot<P, A, N>>();
+    debug_assert_eq!(slot as usize % align_of::<Slot<P, A, N>>(), 0);
+
+    &*slot
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,144 @@
+use crate::common::types::Level;
+
+use std::fmt::Debug;
+
+/// Backing storage of `L2Book`: at most `depth` levels, best first.
+pub trait TopLevels<P, A, const N: usize>: Debug + Clone {
+    fn with_depth(depth: usize) -> Self;
+
+    fn depth(&self) -> usize;
+
+    fn as_slice(&self) -> &[Level<P, A>];
+
+    fn as_mut_slice(&mut self) -> &mut [Level<P, A>];
+
+    /// `pos <= len` and `pos < depth`, the worst level falls out if full
+    fn insert(&mut self, pos: usize, level: Level<P, A>);
+
+    fn remove(&mut self, pos: usize);
+
+    fn clear(&mut self);
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    #[inline(always)]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Heap-allocated once with capacity `depth`, the depth may be chosen at runtime.
+#[derive(Debug, Clone)]
+pub struct VecLevels<P, A> {
+    levels: Vec<Level<P, A>>,
+    depth: usize,
+}
+
+impl<P: Copy + Debug, A: Copy + Debug, const N: usize> TopLevels<P, A, N> for VecLevels<P, A> {
+    fn with_depth(depth: usize) -> Self {
+        VecLevels {
+            levels: Vec::with_capacity(depth),
+            depth,
+        }
+    }
+
+    #[inline(always)]
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    #[inline(always)]
+    fn as_slice(&self) -> &[Level<P, A>] {
+        &self.levels
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [Level<P, A>] {
+        &mut self.levels
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, pos: usize, level: Level<P, A>) {
+        if self.levels.len() == self.depth {
+            self.levels.pop();
+        }
+
+        self.levels.insert(pos, level);
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, pos: usize) {
+        self.levels.remove(pos);
+    }
+
+    #[inline(always)]
+    fn clear(&mut self) {
+        self.levels.clear();
+    }
+}
+
+/// Inline array, no heap and no capacity checks. The depth is always `N`.
+///
+/// An insertion always shifts the tail by one and drops whatever falls out past `N`,
+/// so there is no separate path for a full book.
+#[derive(Debug, Clone, Copy)]
+pub struct ArrayLevels<P, A, const N: usize> {
+    levels: [Level<P, A>; N],
+    len: usize,
+}
+
+impl<P: Copy + Default + Debug, A: Copy + Default + Debug, const N: usize> TopLevels<P, A, N>
+    for ArrayLevels<P, A, N>
+{
+    fn with_depth(depth: usize) -> Self {
+        assert_eq!(depth, N, "Array levels have a fixed depth");
+
+        ArrayLevels {
+            levels: [Level::default(); N],
+            len: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn depth(&self) -> usize {
+        N
+    }
+
+    #[inline(always)]
+    fn as_slice(&self) -> &[Level<P, A>] {
+        &self.levels[..self.len]
+    }
+
+    #[inline(always)]
+    fn as_mut_slice(&mut self) -> &mut [Level<P, A>] {
+        &mut self.levels[..self.len]
+    }
+
+    #[inline(always)]
+    fn insert(&mut self, pos: usize, level: Level<P, A>) {
+        let end = self.len.min(N - 1);
+
+        self.levels.copy_within(pos..end, pos + 1);
+        self.levels[pos] = level;
+        self.len = end + 1;
+    }
+
+    #[inline(always)]
+    fn remove(&mut self, pos: usize) {
+        self.levels.copy_within(pos + 1..self.len, pos);
+        self.len -= 1;
+    }
+
+    #[inline(always)]
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
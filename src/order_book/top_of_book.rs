@@ -0,0 +1,59 @@
+use crate::common::types::{Amount, Level, Price};
+
+/// Copy of the best `N` levels of both sides, e.g. to publish through a `SeqLock`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct TopOfBook<P, A, const N: usize> {
+    bids: [Level<P, A>; N],
+    asks: [Level<P, A>; N],
+    bid_len: usize,
+    ask_len: usize,
+}
+
+impl<P: Price, A: Amount, const N: usize> TopOfBook<P, A, N> {
+    /// Levels beyond `N` are left out
+    pub fn new(bids: &[Level<P, A>], asks: &[Level<P, A>]) -> Self {
+        let mut top = TopOfBook {
+            bid_len: bids.len().min(N),
+            ask_len: asks.len().min(N),
+            ..Self::default()
+        };
+        top.bids[..top.bid_len].copy_from_slice(&bids[..top.bid_len]);
+        top.asks[..top.ask_len].copy_from_slice(&asks[..top.ask_len]);
+
+        top
+    }
+
+    #[inline(always)]
+    pub fn bids(&self) -> &[Level<P, A>] {
+        &self.bids[..self.bid_len]
+    }
+
+    #[inline(always)]
+    pub fn asks(&self) -> &[Level<P, A>] {
+        &self.asks[..self.ask_len]
+    }
+
+    #[inline(always)]
+    pub fn best_bid(&self) -> Option<Level<P, A>> {
+        self.bids().first().copied()
+    }
+
+    #[inline(always)]
+    pub fn best_ask(&self) -> Option<Level<P, A>> {
+        self.asks().first().copied()
+    }
+}
+
+impl<P: Price, A: Amount, const N: usize> Default for TopOfBook<P, A, N> {
+    fn default() -> Self {
+        let empty = Level::new(P::default(), A::zero());
+
+        TopOfBook {
+            bids: [empty; N],
+            asks: [empty; N],
+            bid_len: 0,
+            ask_len: 0,
+        }
+    }
+}
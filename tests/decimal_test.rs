@@ -0,0 +1,56 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{L2Delta, Level};
+use lobotomy::common::{Decimal, ParseDecimalError};
+use lobotomy::order_book::L2BookBuilder;
+
+type D8 = Decimal<8>;
+
+fn d8(s: &str) -> D8 {
+    s.parse().unwrap()
+}
+
+#[test]
+fn decimal_test() {
+    assert_eq!(d8("0.1") + d8("0.2"), d8("0.3"));
+    assert_eq!(d8("100.10"), d8("100.10000000"));
+    assert_eq!(d8("-0.5").mantissa(), -50_000_000);
+    assert_eq!(d8(".5"), d8("0.5"));
+    assert_eq!(d8("+7"), D8::from_mantissa(700_000_000));
+
+    assert_eq!(d8("65432.10000000").to_string(), "65432.1");
+    assert_eq!(d8("0.00000001").to_string(), "0.00000001");
+    assert_eq!(d8("-1.25").to_string(), "-1.25");
+    assert_eq!(d8("42.000").to_string(), "42");
+
+    assert_eq!("".parse::<D8>(), Err(ParseDecimalError::Empty));
+    assert_eq!("1.2x".parse::<D8>(), Err(ParseDecimalError::InvalidDigit));
+    assert_eq!(
+        "0.000000001".parse::<D8>(),
+        Err(ParseDecimalError::Precision)
+    );
+    assert_eq!(d8("0.0000000100"), d8("0.00000001"));
+    assert_eq!(
+        "100000000000000".parse::<D8>(),
+        Err(ParseDecimalError::Overflow)
+    );
+
+    assert_eq!(f64::from(d8("1.5")), 1.5);
+}
+
+#[test]
+fn decimal_book_test() {
+    let tick_size = d8("0.01");
+    let mut bid = L2BookBuilder::<D8, D8, 2, true>::new(D8::ZERO, None, tick_size, None);
+
+    // Snapshot and diff format the same price differently
+    bid.apply_l2_snapshot(&[
+        Level::new(d8("65000.10000000"), d8("0.1")),
+        Level::new(d8("64999.99"), d8("0.2")),
+    ]);
+    bid.apply_l2_upserts(&[Level::new(d8("65000.1"), d8("0.3"))]);
+    bid.apply_l2_deltas(&[L2Delta::new(d8("64999.990"), -d8("0.2"))]);
+
+    assert_eq!(bid.book().levels(), &[Level::new(d8("65000.1"), d8("0.3"))]);
+    assert_eq!(bid.get_level(d8("64999.99")).amt, D8::ZERO);
+}
@@ -0,0 +1,117 @@
+extern crate lobotomy;
+
+use lobotomy::common::types;
+use lobotomy::nasdaq::ItchIntoL2Deltas;
+
+use itchy::{AddOrder, ArrayString, Body, Message, Price4, ReplaceOrder, Side};
+
+fn message(body: Body) -> Message {
+    Message {
+        tag: 0,
+        stock_locate: 0,
+        tracking_number: 0,
+        timestamp: 0,
+        body,
+    }
+}
+
+#[test]
+fn overfill_test() {
+    let mut itch = ItchIntoL2Deltas::with_capacity(16);
+    let mut l2_deltas = Vec::new();
+    let mut anomalies = Vec::new();
+
+    let history = [
+        message(Body::AddOrder(AddOrder {
+            reference: 1,
+            side: Side::Sell,
+            shares: 10,
+            stock: ArrayString::from("AAPL").unwrap(),
+            price: Price4::from(1_000_000),
+            mpid: None,
+        })),
+        message(Body::OrderExecuted {
+            reference: 1,
+            executed: 4,
+            match_number: 0,
+        }),
+        // Duplicated execution, only the remaining shares leave the level
+        message(Body::OrderCancelled {
+            reference: 1,
+            cancelled: 8,
+        }),
+    ];
+
+    for msg in history.iter() {
+        itch.apply_message_with(
+            msg,
+            |_, px, amt_delta, order_count_delta| {
+                l2_deltas.push((px.raw(), *amt_delta, *order_count_delta))
+            },
+            |anomaly| anomalies.push(*anomaly),
+        );
+    }
+
+    assert_eq!(
+        l2_deltas,
+        vec![(1_000_000, 10, 1), (1_000_000, -4, 0), (1_000_000, -6, -1)]
+    );
+
+    let anomalies = anomalies
+        .iter()
+        .map(|a| (a.side, a.px.0.raw(), a.prev_amt, a.amt_delta))
+        .collect::<Vec<_>>();
+    assert_eq!(anomalies, vec![(types::Side::Ask, 1_000_000, 6, -8)]);
+}
+
+#[test]
+fn removed_orders_test() {
+    let mut itch = ItchIntoL2Deltas::with_capacity(16);
+    let mut l2_deltas = Vec::new();
+
+    let add = |reference, shares| {
+        message(Body::AddOrder(AddOrder {
+            reference,
+            side: Side::Buy,
+            shares,
+            stock: ArrayString::from("AAPL").unwrap(),
+            price: Price4::from(1_000_000),
+            mpid: None,
+        }))
+    };
+    let history = [
+        add(1, 10),
+        add(2, 20),
+        message(Body::DeleteOrder { reference: 1 }),
+        // Deleted and replaced orders are gone from the pool, repeated messages are ignored
+        message(Body::DeleteOrder { reference: 1 }),
+        message(Body::ReplaceOrder(ReplaceOrder {
+            old_reference: 2,
+            new_reference: 3,
+            shares: 5,
+            price: Price4::from(999_900),
+        })),
+        message(Body::OrderCancelled {
+            reference: 2,
+            cancelled: 20,
+        }),
+        message(Body::DeleteOrder { reference: 2 }),
+    ];
+
+    for msg in history.iter() {
+        itch.apply_message(msg, |_, px, amt_delta, order_count_delta| {
+            l2_deltas.push((px.raw(), *amt_delta, *order_count_delta))
+        });
+    }
+
+    assert_eq!(
+        l2_deltas,
+        vec![
+            (1_000_000, 10, 1),
+            (1_000_000, 20, 1),
+            (1_000_000, -10, -1),
+            (1_000_000, -20, -1),
+            (999_900, 5, 1),
+        ]
+    );
+}
@@ -0,0 +1,675 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{Amount, L2Delta, L3Delta, Level, Price, Side};
+use lobotomy::common::Decimal;
+use lobotomy::order_book::{
+    ArrayLevels, BucketGrid, BucketLadder, DenseLevels, DynL2BookBuilder, L2BookBuilder, OrderBook,
+    OrderStore, TopChange,
+};
+use rand::Rng;
+use std::collections::BTreeMap;
+
+#[test]
+fn apply_l3_deltas_test() {
+    let mut lob = OrderBook::<f64, f64, 2>::new(0.0, None, 0.01, None);
+    let mut orders = OrderStore::new(16);
+
+    let l3_deltas = [
+        L3Delta::Add {
+            id: 1,
+            side: Side::Bid,
+            px: 100.0,
+            amt: 10.0,
+        },
+        L3Delta::Add {
+            id: 2,
+            side: Side::Bid,
+            px: 99.0,
+            amt: 5.0,
+        },
+        L3Delta::Add {
+            id: 3,
+            side: Side::Bid,
+            px: 98.0,
+            amt: 1.0,
+        },
+        L3Delta::Add {
+            id: 4,
+            side: Side::Ask,
+            px: 101.0,
+            amt: 7.0,
+        },
+        L3Delta::Reduce { id: 1, amt: 4.0 },
+        L3Delta::Reduce { id: 4, amt: 7.0 },
+        L3Delta::Replace {
+            id: 2,
+            new_id: 5,
+            px: 102.0,
+            amt: 3.0,
+        },
+        L3Delta::Delete { id: 1 },
+        L3Delta::Delete { id: 42 },
+    ];
+
+    for l3_delta in l3_deltas.iter() {
+        lob.apply_l3_deltas(&mut orders, std::slice::from_ref(l3_delta));
+    }
+    let (bid, ask) = (lob.bid(), lob.ask());
+
+    assert_eq!(
+        bid.book().levels(),
+        &[
+            Level {
+                px: 102.0,
+                amt: 3.0,
+                order_count: 1,
+            },
+            Level {
+                px: 98.0,
+                amt: 1.0,
+                order_count: 1,
+            }
+        ]
+    );
+    assert_eq!(bid.get_level(102.0).amt, 3.0);
+    assert_eq!(bid.get_level(100.0).amt, 0.0);
+    assert!(ask.book().levels().is_empty());
+    assert!(orders.get(4).is_none());
+    assert_eq!(orders.get(5).unwrap().side, Side::Bid);
+}
+
+#[test]
+fn duplicate_add_test() {
+    let mut bid = L2BookBuilder::<f64, f64, 2, true>::new(0.0, None, 0.01, None);
+    let mut orders = OrderStore::new(16);
+
+    // The second add replaces the first order, which leaves its level
+    bid.apply_l3_deltas(
+        &mut orders,
+        &[
+            L3Delta::Add {
+                id: 1,
+                side: Side::Bid,
+                px: 100.0,
+                amt: 10.0,
+            },
+            L3Delta::Add {
+                id: 1,
+                side: Side::Bid,
+                px: 99.0,
+                amt: 5.0,
+            },
+        ],
+    );
+
+    assert_eq!(bid.get_level(100.0).amt, 0.0);
+    assert_eq!(bid.get_level(100.0).order_count, 0);
+    assert_eq!(bid.get_level(99.0).amt, 5.0);
+    assert_eq!(bid.get_level(99.0).order_count, 1);
+    assert_eq!(bid.book().levels().len(), 1);
+    assert_eq!(orders.get(1).unwrap().px, 99.0);
+}
+
+#[test]
+fn top_levels_amounts_test() {
+    let mut ask = L2BookBuilder::<f64, f64, 2, false>::new(0.0, None, 0.01, None);
+
+    ask.apply_l2_snapshot(&[
+        Level::new(101.0, 1.0),
+        Level::new(102.0, 2.0),
+        Level::new(103.0, 3.0),
+    ]);
+    ask.apply_l2_upserts(&[Level::new(102.0, 5.0)]);
+    ask.apply_l2_deltas(&[L2Delta::new(101.0, 0.5)]);
+
+    let top = |ask: &L2BookBuilder<f64, f64, 2, false>| {
+        ask.book()
+            .levels()
+            .iter()
+            .map(|lvl| (lvl.px, lvl.amt))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(top(&ask), vec![(101.0, 1.5), (102.0, 5.0)]);
+
+    // The refilled worst level comes with its amount
+    ask.apply_l2_upserts(&[Level::new(101.0, 0.0)]);
+    assert_eq!(top(&ask), vec![(102.0, 5.0), (103.0, 3.0)]);
+
+    ask.apply_l2_deltas(&[L2Delta::new(103.0, -1.0)]);
+    assert_eq!(top(&ask), vec![(102.0, 5.0), (103.0, 2.0)]);
+}
+
+#[test]
+fn bounded_window_test() {
+    let tick_size = 0.01;
+    let mut bounded = L2BookBuilder::<f64, f64, 8, true>::new(0.0, None, tick_size, Some(1024));
+    let mut unbounded = L2BookBuilder::<f64, f64, 8, true>::new(0.0, None, tick_size, None);
+
+    // The market drifts far away from the window, with occasional wild prints
+    let mut mid = 100.0;
+    for i in 0..100_000 {
+        mid += rand::thread_rng().gen_range(-0.05..0.06);
+        let px = if i % 1000 == 0 {
+            mid * 3.0
+        } else {
+            mid - rand::thread_rng().gen_range(0.0..20.0)
+        };
+        let px = (px / tick_size).round() * tick_size;
+        let amt = if rand::thread_rng().gen_bool(0.3) {
+            0.0
+        } else {
+            rand::thread_rng().gen_range(1.0..100.0)
+        };
+
+        let level = [Level::new(px, amt)];
+        bounded.apply_l2_upserts(&level);
+        unbounded.apply_l2_upserts(&level);
+
+        assert_eq!(bounded.book().levels(), unbounded.book().levels());
+        assert_eq!(bounded.get_level(px).amt, unbounded.get_level(px).amt);
+    }
+
+    assert!(bounded.overflow_len() > 0);
+    assert_eq!(
+        bounded
+            .top_levels_from_map::<8>()
+            .map(|lvl| lvl.map(|(px, lvl)| (px, lvl.amt))),
+        unbounded
+            .top_levels_from_map::<8>()
+            .map(|lvl| lvl.map(|(px, lvl)| (px, lvl.amt)))
+    );
+}
+
+#[test]
+fn amount_anomaly_test() {
+    assert_eq!(5_u32.checked_apply_delta(&-5), Some(0));
+    assert_eq!(5_u32.checked_apply_delta(&-6), None);
+    assert_eq!(u32::MAX.checked_apply_delta(&1), None);
+    assert_eq!(5_u64.checked_apply_delta(&-6), None);
+    assert_eq!(5_i64.checked_apply_delta(&-6), None);
+    assert_eq!(i64::MAX.checked_apply_delta(&1), None);
+    // Float residue is not an anomaly
+    assert_eq!(0.3_f64.checked_apply_delta(&-(0.1 + 0.2)), Some(0.0));
+    assert_eq!((0.1 + 0.2_f64).checked_apply_delta(&-0.3), Some(0.0));
+    assert_eq!(0.1_f64.checked_apply_delta(&-0.2), None);
+    assert_eq!(5_u32.apply_delta(&-6), 0);
+
+    let mut ask = L2BookBuilder::<i64, u32, 2, false>::new(0, None, 1, None);
+    ask.apply_l2_snapshot(&[Level::new(101, 10), Level::new(102, 20)]);
+
+    let mut anomalies = Vec::new();
+    ask.apply_l2_deltas_with(
+        &[
+            L2Delta::new(101, -15),
+            L2Delta::new(102, -5),
+            L2Delta::new(103, -1),
+        ],
+        |anomaly| anomalies.push(*anomaly),
+    );
+
+    let anomalies = anomalies
+        .iter()
+        .map(|a| (a.side, a.px, a.prev_amt, a.amt_delta))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        anomalies,
+        vec![(Side::Ask, 101, 10, -15), (Side::Ask, 103, 0, -1)]
+    );
+
+    // Clamped levels are gone from the book rather than wrapped around
+    let top = ask
+        .book()
+        .levels()
+        .iter()
+        .map(|lvl| (lvl.px, lvl.amt))
+        .collect::<Vec<_>>();
+    assert_eq!(top, vec![(102, 15)]);
+    assert_eq!(ask.get_level(101).amt, 0);
+}
+
+#[test]
+fn order_overfill_test() {
+    let mut ask = L2BookBuilder::<i64, u32, 2, false>::new(0, None, 1, None);
+    let mut orders = OrderStore::new(16);
+
+    let mut anomalies = Vec::new();
+    ask.apply_l3_deltas_with(
+        &mut orders,
+        &[
+            L3Delta::Add {
+                id: 1,
+                side: Side::Ask,
+                px: 101,
+                amt: 10,
+            },
+            L3Delta::Add {
+                id: 2,
+                side: Side::Ask,
+                px: 101,
+                amt: 20,
+            },
+            L3Delta::Reduce { id: 1, amt: 15 },
+        ],
+        |anomaly| anomalies.push(*anomaly),
+    );
+
+    let anomalies = anomalies
+        .iter()
+        .map(|a| (a.side, a.px, a.prev_amt, a.amt_delta))
+        .collect::<Vec<_>>();
+    assert_eq!(anomalies, vec![(Side::Ask, 101, 10, -15)]);
+
+    // Only the remaining amount of the order leaves the level
+    assert_eq!(ask.get_level(101).amt, 20);
+    assert_eq!(ask.get_level(101).order_count, 1);
+    assert!(orders.get(1).is_none());
+}
+
+#[test]
+fn order_count_test() {
+    let mut bid = L2BookBuilder::<f64, f64, 2, true>::new(0.0, None, 0.01, None);
+    let mut orders = OrderStore::new(16);
+
+    let add = |id, px, amt| L3Delta::Add {
+        id,
+        side: Side::Bid,
+        px,
+        amt,
+    };
+    bid.apply_l3_deltas(
+        &mut orders,
+        &[
+            add(1, 100.0, 10.0),
+            add(2, 100.0, 20.0),
+            add(3, 100.0, 30.0),
+            add(4, 99.0, 5.0),
+            add(5, 98.0, 1.0),
+        ],
+    );
+
+    let top = |bid: &L2BookBuilder<f64, f64, 2, true>| {
+        bid.book()
+            .levels()
+            .iter()
+            .map(|lvl| (lvl.px, lvl.amt, lvl.order_count))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(top(&bid), vec![(100.0, 60.0, 3), (99.0, 5.0, 1)]);
+
+    // A partial fill keeps the order, a full one removes it
+    bid.apply_l3_deltas(
+        &mut orders,
+        &[
+            L3Delta::Reduce { id: 1, amt: 4.0 },
+            L3Delta::Reduce { id: 2, amt: 20.0 },
+            L3Delta::Delete { id: 4 },
+        ],
+    );
+    assert_eq!(top(&bid), vec![(100.0, 36.0, 2), (98.0, 1.0, 1)]);
+
+    bid.apply_l3_deltas(
+        &mut orders,
+        &[L3Delta::Replace {
+            id: 3,
+            new_id: 6,
+            px: 98.0,
+            amt: 30.0,
+        }],
+    );
+    assert_eq!(top(&bid), vec![(100.0, 6.0, 1), (98.0, 31.0, 2)]);
+    assert_eq!(bid.get_level(98.0).order_count, 2);
+
+    // Venue-published counts are taken as is
+    bid.apply_l2_upserts(&[Level {
+        px: 101.0,
+        amt: 7.0,
+        order_count: 4,
+    }]);
+    assert_eq!(top(&bid), vec![(101.0, 7.0, 4), (100.0, 6.0, 1)]);
+
+    // Removing the top refills the worst level with its count
+    bid.apply_l2_upserts(&[Level::new(101.0, 0.0)]);
+    assert_eq!(top(&bid), vec![(100.0, 6.0, 1), (98.0, 31.0, 2)]);
+}
+
+#[test]
+fn array_levels_test() {
+    fn run<const N: usize>() {
+        let tick_size = 0.01;
+        let mut vec_book = L2BookBuilder::<f64, f64, N, false>::new(0.0, None, tick_size, None);
+        let mut array_book =
+            L2BookBuilder::<f64, f64, N, false, DenseLevels<f64>, ArrayLevels<f64, f64, N>>::new(
+                0.0, None, tick_size, None,
+            );
+
+        for _ in 0..100_000 {
+            let px = rand::thread_rng().gen_range(100..200) as f64 * tick_size;
+            let amt = if rand::thread_rng().gen_bool(0.4) {
+                0.0
+            } else {
+                rand::thread_rng().gen_range(1.0..100.0)
+            };
+
+            let level = [Level::new(px, amt)];
+            vec_book.apply_l2_upserts(&level);
+            array_book.apply_l2_upserts(&level);
+
+            assert_eq!(vec_book.book().levels(), array_book.book().levels());
+        }
+    }
+
+    run::<1>();
+    run::<2>();
+    run::<16>();
+}
+
+#[test]
+fn count_better_test() {
+    fn check<P: Price + Copy>(pxs: &[P], px: &P) {
+        let levels = pxs
+            .iter()
+            .map(|px| Level::new(*px, 1.0))
+            .collect::<Vec<_>>();
+
+        for len in 0..=levels.len() {
+            let levels = &levels[..len];
+            assert_eq!(
+                P::count_better::<true, f64>(levels, px),
+                levels.iter().filter(|lvl| lvl.px > *px).count()
+            );
+            assert_eq!(
+                P::count_better::<false, f64>(levels, px),
+                levels.iter().filter(|lvl| lvl.px < *px).count()
+            );
+        }
+    }
+
+    for _ in 0..1_000 {
+        let pxs = (0..17)
+            .map(|_| rand::thread_rng().gen_range(-10..10_i64))
+            .collect::<Vec<_>>();
+        let px = rand::thread_rng().gen_range(-10..10_i64);
+
+        check(&pxs, &px);
+        check(
+            &pxs.iter().map(|px| *px as f64 * 0.01).collect::<Vec<_>>(),
+            &(px as f64 * 0.01),
+        );
+        check(
+            &pxs.iter()
+                .map(|px| Decimal::<2>::from_mantissa(*px))
+                .collect::<Vec<_>>(),
+            &Decimal::<2>::from_mantissa(px),
+        );
+    }
+
+    check(&[i64::MIN, i64::MAX, 0, -1, 1], &0);
+    check(&[i64::MIN, i64::MAX, 0], &i64::MIN);
+    check(&[f64::MIN, f64::MAX, -0.0, 0.0, 1e-300], &0.0);
+}
+
+#[test]
+fn runtime_depth_test() {
+    let tick_size = 0.01;
+    let mut dynamic = DynL2BookBuilder::<f64, f64, true>::with_depth(0.0, None, tick_size, None, 8);
+    let mut fixed = L2BookBuilder::<f64, f64, 8, true>::new(0.0, None, tick_size, None);
+    assert_eq!(dynamic.book().depth(), 8);
+
+    let mut top = Vec::new();
+
+    for _ in 0..100_000 {
+        let px = rand::thread_rng().gen_range(100..200) as f64 * tick_size;
+        let amt_delta = rand::thread_rng().gen_range(-50.0..100.0);
+
+        let delta = [L2Delta::new(px, amt_delta)];
+        dynamic.apply_l2_deltas(&delta);
+        fixed.apply_l2_deltas(&delta);
+
+        assert_eq!(dynamic.book().levels(), fixed.book().levels());
+    }
+
+    dynamic.top_levels_from_map_into(8, &mut top);
+    let fixed_top = fixed.top_levels_from_map::<8>();
+    assert_eq!(
+        top.iter()
+            .map(|(px, level)| (*px, level.amt))
+            .collect::<Vec<_>>(),
+        fixed_top
+            .iter()
+            .flatten()
+            .map(|(px, level)| (*px, level.amt))
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn range_queries_test() {
+    let mut ask = L2BookBuilder::<f64, f64, 2, false>::new(0.0, None, 0.01, None);
+    let mut bid = L2BookBuilder::<f64, f64, 2, true>::new(0.0, None, 0.01, None);
+
+    let levels = |pxs: &[(f64, f64)]| {
+        pxs.iter()
+            .map(|(px, amt)| Level::new(*px, *amt))
+            .collect::<Vec<_>>()
+    };
+    ask.apply_l2_snapshot(&levels(&[
+        (100.0, 1.0),
+        (100.02, 2.0),
+        (100.05, 3.0),
+        (101.0, 4.0),
+    ]));
+    bid.apply_l2_snapshot(&levels(&[(99.99, 1.0), (99.98, 2.0), (90.0, 3.0)]));
+
+    // Beyond the depth of the book, in ticks
+    let pxs = |iter: &mut dyn Iterator<Item = Level<f64, f64>>| {
+        iter.map(|lvl| (lvl.px * 100.0).round() as i64)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(pxs(&mut ask.levels()), vec![10000, 10002, 10005, 10100]);
+    assert_eq!(pxs(&mut bid.levels()), vec![9999, 9998, 9000]);
+
+    assert_eq!(pxs(&mut ask.levels_within_ticks(2)), vec![10000, 10002]);
+    assert_eq!(
+        pxs(&mut ask.levels_within_ticks(5)),
+        vec![10000, 10002, 10005]
+    );
+    assert_eq!(pxs(&mut bid.levels_within_ticks(0)), vec![9999]);
+
+    // 10bps of 100.0 is 0.1
+    assert_eq!(
+        pxs(&mut ask.levels_within_bps(10.0)),
+        vec![10000, 10002, 10005]
+    );
+    assert_eq!(pxs(&mut bid.levels_within_bps(100.0)), vec![9999, 9998]);
+
+    assert_eq!(ask.cumulative_amount(100.04), 3.0);
+    assert_eq!(ask.cumulative_amount(101.0), 10.0);
+    assert_eq!(ask.cumulative_amount(99.0), 0.0);
+    assert_eq!(bid.cumulative_amount(99.98), 3.0);
+    assert_eq!(bid.cumulative_amount(0.0), 6.0);
+
+    let empty = L2BookBuilder::<f64, f64, 2, true>::new(0.0, None, 0.01, None);
+    assert_eq!(empty.levels_within_ticks(10).count(), 0);
+}
+
+#[test]
+fn cost_to_fill_test() {
+    let mut ask = L2BookBuilder::<f64, f64, 2, false>::new(0.0, None, 0.5, None);
+    assert_eq!(ask.cost_to_fill(1.0), None);
+
+    ask.apply_l2_snapshot(&[
+        Level::new(100.0, 1.0),
+        Level::new(100.5, 2.0),
+        Level::new(101.0, 3.0),
+    ]);
+
+    let fill = ask.cost_to_fill(2.0).unwrap();
+    assert_eq!(fill.filled_amt, 2.0);
+    assert_eq!(fill.avg_px, 100.25);
+    assert_eq!((fill.best_px, fill.worst_px), (100.0, 100.5));
+    assert_eq!(fill.slippage_bps(), 25.0);
+
+    // Beyond the depth of the book and the whole side
+    let fill = ask.cost_to_fill(10.0).unwrap();
+    assert_eq!(fill.filled_amt, 6.0);
+    assert_eq!(fill.avg_px, (100.0 + 201.0 + 303.0) / 6.0);
+    assert_eq!(fill.worst_px, 101.0);
+
+    assert_eq!(ask.cost_to_fill(0.0), None);
+    assert_eq!(ask.amount_within_bps(50.0), 3.0);
+    assert_eq!(ask.amount_within_bps(100.0), 6.0);
+}
+
+#[test]
+fn bucket_ladder_test() {
+    let tick_size = 0.01;
+    let mut ask = L2BookBuilder::<f64, f64, 4, false>::new(0.0, None, tick_size, None);
+    ask.apply_l2_snapshot(&[
+        Level {
+            px: 100.0,
+            amt: 1.0,
+            order_count: 1,
+        },
+        Level {
+            px: 100.09,
+            amt: 2.0,
+            order_count: 2,
+        },
+    ]);
+
+    // Attached after the snapshot, catches up with the levels
+    let mut ask = ask.with_observer(BucketLadder::new(BucketGrid::Ticks(10), tick_size));
+    let buckets = ask.observer().iter().collect::<Vec<_>>();
+    assert_eq!(buckets.len(), 1);
+    assert_eq!(buckets[0].0, 1000);
+    assert_eq!(buckets[0].1.amt, 3.0);
+    assert_eq!(buckets[0].1.order_count, 3);
+    assert!((ask.observer().bucket_px(1000) - 100.0).abs() < 1e-9);
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..1000 {
+        let px = 100.0 + rng.gen_range(0..100) as f64 * tick_size;
+        let prev_amt = ask.get_level(px).amt;
+        let amt_delta = if prev_amt > 0.0 && rng.gen_bool(0.5) {
+            -prev_amt
+        } else {
+            rng.gen_range(1..10) as f64
+        };
+        ask.apply_l2_deltas(&[L2Delta {
+            px,
+            amt_delta,
+            order_count_delta: amt_delta.signum() as i32,
+        }]);
+
+        let mut expected = BTreeMap::new();
+        for lvl in ask.levels() {
+            *expected
+                .entry(ask.observer().bucket_of(&lvl.px))
+                .or_insert(0.0) += lvl.amt;
+        }
+        let buckets = ask
+            .observer()
+            .iter()
+            .map(|(bucket, level)| (bucket, level.amt))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(buckets, expected);
+    }
+
+    // Snapshots start over
+    ask.apply_l2_snapshot(&[Level::new(100.5, 5.0)]);
+    assert_eq!(ask.observer().len(), 1);
+    assert_eq!(ask.observer().get(1005).amt, 5.0);
+
+    // Price grid, best bucket first for bids
+    let bid = L2BookBuilder::<f64, f64, 4, true>::new(0.0, None, tick_size, None);
+    let mut bid = bid.with_observer(BucketLadder::new(
+        BucketGrid::Prices(vec![99.0, 99.5, 100.0]),
+        tick_size,
+    ));
+    bid.apply_l2_upserts(&[
+        Level::new(99.99, 1.0),
+        Level::new(99.5, 2.0),
+        Level::new(99.2, 3.0),
+        Level::new(98.0, 4.0),
+    ]);
+    let buckets = bid
+        .observer()
+        .iter()
+        .rev()
+        .map(|(bucket, level)| (bucket, level.amt))
+        .collect::<Vec<_>>();
+    assert_eq!(buckets, vec![(1, 3.0), (0, 3.0), (-1, 4.0)]);
+    assert_eq!(bid.observer().bucket_px(-1), f64::NEG_INFINITY);
+
+    // Geometric buckets, 100bps apart
+    let ladder = BucketLadder::<f64, f64>::new(BucketGrid::Bps(100.0), tick_size);
+    let bucket = ladder.bucket_of(&100.0);
+    let from_px = ladder.bucket_px(bucket);
+    assert!(from_px <= 100.0 && 100.0 < from_px * 1.01);
+    assert_eq!(ladder.bucket_of(&(from_px * 1.005)), bucket);
+    assert_eq!(ladder.bucket_of(&(from_px * 1.015)), bucket + 1);
+
+    // 0.1 + 0.2 - 0.1 - 0.2 is not zero in f64, the bucket goes away with its last level
+    let ask = L2BookBuilder::<f64, f64, 4, false>::new(0.0, None, tick_size, None);
+    let mut ask = ask.with_observer(BucketLadder::new(BucketGrid::Ticks(10), tick_size));
+    ask.apply_l2_upserts(&[Level::new(100.01, 0.1), Level::new(100.02, 0.2)]);
+    assert_eq!(ask.observer().len(), 1);
+    ask.apply_l2_upserts(&[Level::new(100.01, 0.0), Level::new(100.02, 0.0)]);
+    assert!(ask.observer().is_empty());
+}
+
+#[test]
+fn top_changes_test() {
+    let tick_size = 0.01;
+    let mut bid = L2BookBuilder::<f64, f64, 4, true>::new(0.0, None, tick_size, None);
+    bid.apply_l2_snapshot(&[Level::new(100.0, 1.0), Level::new(99.0, 2.0)]);
+    assert!(bid.best_px_changed());
+    assert_eq!(bid.top_changes().len(), 2);
+
+    // No-op changes are not reported
+    bid.apply_l2_upserts(&[Level::new(99.0, 2.0)]);
+    assert!(bid.top_changes().is_empty());
+    assert!(!bid.best_px_changed());
+
+    bid.apply_l2_deltas(&[L2Delta::new(99.0, 1.0)]);
+    assert!(!bid.best_px_changed());
+    assert_eq!(
+        bid.top_changes(),
+        &[TopChange::Update {
+            pos: 1,
+            prev: Level::new(99.0, 2.0),
+            level: Level::new(99.0, 3.0),
+        }]
+    );
+
+    // Replaying the changes onto a copy of the top keeps it in sync
+    let mut rng = rand::thread_rng();
+    let mut top = bid.book().levels().to_vec();
+    for _ in 0..1000 {
+        let prev_best_px = top.first().map(|lvl| lvl.px);
+        let px = 99.0 + rng.gen_range(0..20) as f64 * 0.1;
+        let prev_amt = bid.get_level(px).amt;
+        let amt_delta = if prev_amt > 0.0 && rng.gen_bool(0.5) {
+            -prev_amt
+        } else {
+            rng.gen_range(1..10) as f64
+        };
+        bid.apply_l2_deltas(&[L2Delta::new(px, amt_delta)]);
+
+        for change in bid.top_changes() {
+            match *change {
+                TopChange::Insert { pos, level } => top.insert(pos, level),
+                TopChange::Remove { pos, level } => assert_eq!(top.remove(pos), level),
+                TopChange::Update { pos, prev, level } => {
+                    assert_eq!(top[pos], prev);
+                    top[pos] = level;
+                }
+            }
+        }
+        assert_eq!(top, bid.book().levels());
+        assert_eq!(
+            bid.best_px_changed(),
+            prev_best_px != top.first().map(|lvl| lvl.px)
+        );
+    }
+}
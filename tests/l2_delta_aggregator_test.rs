@@ -0,0 +1,115 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{L2Delta, L3Delta, Level, Side};
+use lobotomy::order_book::{L2BookBuilder, L2DeltaAggregator, OrderStore};
+
+#[test]
+fn l2_delta_aggregator_test() {
+    let tick_size = 0.01;
+    let mut bid = L2BookBuilder::<f64, f64, 4, true>::new(0.0, None, tick_size, None);
+    let mut ask = L2BookBuilder::<f64, f64, 4, false>::new(0.0, None, tick_size, None);
+    let mut orders = OrderStore::new(16);
+    let mut aggregator = L2DeltaAggregator::new(16);
+
+    // A sweep touching the same prices repeatedly
+    aggregator.push_l3_deltas(
+        &mut orders,
+        &[
+            L3Delta::Add {
+                id: 1,
+                side: Side::Bid,
+                px: 100.0,
+                amt: 10.0,
+            },
+            L3Delta::Add {
+                id: 2,
+                side: Side::Bid,
+                px: 100.0,
+                amt: 5.0,
+            },
+            L3Delta::Add {
+                id: 3,
+                side: Side::Ask,
+                px: 101.0,
+                amt: 7.0,
+            },
+            L3Delta::Reduce { id: 1, amt: 4.0 },
+            L3Delta::Add {
+                id: 4,
+                side: Side::Bid,
+                px: 99.0,
+                amt: 1.0,
+            },
+            L3Delta::Delete { id: 4 },
+        ],
+    );
+    aggregator.push(&Side::Ask, &L2Delta::new(102.0, 3.0));
+
+    aggregator.flush(&mut bid, &mut ask);
+    assert!(aggregator.is_empty());
+
+    assert_eq!(
+        bid.book().levels(),
+        &[Level {
+            px: 100.0,
+            amt: 11.0,
+            order_count: 2,
+        }]
+    );
+    assert_eq!(bid.get_level(100.0).amt, 11.0);
+    assert_eq!(
+        ask.book().levels(),
+        &[
+            Level {
+                px: 101.0,
+                amt: 7.0,
+                order_count: 1,
+            },
+            Level::new(102.0, 3.0)
+        ]
+    );
+
+    aggregator.push_l3_deltas(
+        &mut orders,
+        &[
+            L3Delta::Replace {
+                id: 3,
+                new_id: 5,
+                px: 100.5,
+                amt: 2.0,
+            },
+            L3Delta::Delete { id: 2 },
+        ],
+    );
+    aggregator.flush(&mut bid, &mut ask);
+
+    assert_eq!(bid.get_level(100.0).amt, 6.0);
+    assert_eq!(
+        ask.book().levels(),
+        &[
+            Level {
+                px: 100.5,
+                amt: 2.0,
+                order_count: 1,
+            },
+            Level::new(102.0, 3.0)
+        ]
+    );
+}
+
+#[test]
+fn nan_price_test() {
+    let tick_size = 0.01;
+    let mut bid = L2BookBuilder::<f64, f64, 4, true>::new(0.0, None, tick_size, None);
+    let mut ask = L2BookBuilder::<f64, f64, 4, false>::new(0.0, None, tick_size, None);
+    let mut aggregator = L2DeltaAggregator::new(16);
+
+    // A corrupted price is sorted, not a panic
+    for px in [100.0, f64::NAN, 99.0] {
+        aggregator.push(&Side::Bid, &L2Delta::new(px, 1.0));
+    }
+    aggregator.flush(&mut bid, &mut ask);
+
+    assert_eq!(bid.get_level(100.0).amt, 1.0);
+    assert_eq!(bid.get_level(99.0).amt, 1.0);
+}
@@ -0,0 +1,115 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{L2Delta, Level, Side};
+use lobotomy::order_book::{L2BookBuilder, L3Book};
+
+#[test]
+fn l3_book_test() {
+    let tick_size = 0.01;
+    let mut l3_book = L3Book::<f64, f64>::new(100.0, tick_size, 16);
+    let mut bid_builder = L2BookBuilder::<f64, f64, 4, true>::new(0.0, None, tick_size, None);
+    let mut ask_builder = L2BookBuilder::<f64, f64, 4, false>::new(0.0, None, tick_size, None);
+
+    let mut apply = |side: &Side, delta: &L2Delta<f64, f64>| match side {
+        Side::Bid => bid_builder.apply_l2_deltas(std::slice::from_ref(delta)),
+        Side::Ask => ask_builder.apply_l2_deltas(std::slice::from_ref(delta)),
+    };
+
+    l3_book.add(1, Side::Bid, 100.0, 10.0, &mut apply);
+    l3_book.add(2, Side::Bid, 100.0, 20.0, &mut apply);
+    l3_book.add(3, Side::Bid, 100.0, 30.0, &mut apply);
+    l3_book.add(4, Side::Bid, 99.0, 5.0, &mut apply);
+    l3_book.add(5, Side::Ask, 101.0, 7.0, &mut apply);
+
+    let ids = |book: &L3Book<f64, f64>| {
+        book.level_orders(Side::Bid, 100.0)
+            .map(|o| o.id)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(ids(&l3_book), vec![1, 2, 3]);
+
+    let position = l3_book.queue_position(3).unwrap();
+    assert_eq!(position.orders_ahead, 2);
+    assert_eq!(position.amt_ahead, 30.0);
+
+    // Reducing keeps priority, increasing loses it
+    l3_book.modify(1, 5.0, &mut apply);
+    assert_eq!(ids(&l3_book), vec![1, 2, 3]);
+    l3_book.modify(2, 25.0, &mut apply);
+    assert_eq!(ids(&l3_book), vec![1, 3, 2]);
+
+    l3_book.execute(1, 5.0, &mut apply);
+    assert!(l3_book.order(1).is_none());
+    assert_eq!(ids(&l3_book), vec![3, 2]);
+
+    l3_book.cancel(3, 10.0, &mut apply);
+    assert_eq!(l3_book.order(3).unwrap().amt, 20.0);
+
+    l3_book.replace(2, 6, 99.0, 15.0, &mut apply);
+    assert_eq!(ids(&l3_book), vec![3]);
+    assert_eq!(
+        l3_book
+            .level_orders(Side::Bid, 99.0)
+            .map(|o| o.id)
+            .collect::<Vec<_>>(),
+        vec![4, 6]
+    );
+    assert_eq!(l3_book.level_amount(Side::Bid, 99.0), 20.0);
+    assert_eq!(l3_book.level_order_count(Side::Bid, 99.0), 2);
+
+    l3_book.delete(5, &mut apply);
+    l3_book.delete(42, &mut apply);
+
+    assert_eq!(
+        bid_builder.book().levels(),
+        &[
+            Level {
+                px: 100.0,
+                amt: 20.0,
+                order_count: 1,
+            },
+            Level {
+                px: 99.0,
+                amt: 20.0,
+                order_count: 2,
+            }
+        ]
+    );
+    assert_eq!(bid_builder.get_level(100.0).amt, 20.0);
+    assert_eq!(bid_builder.get_level(99.0).amt, 20.0);
+    assert!(ask_builder.book().levels().is_empty());
+}
+
+#[test]
+fn l3_book_overfill_test() {
+    let mut l3_book = L3Book::<i64, u32>::new(100, 1, 16);
+    let mut l2_deltas = Vec::new();
+    let mut anomalies = Vec::new();
+
+    l3_book.add(1, Side::Ask, 101, 10, |_, delta| l2_deltas.push(*delta));
+    l3_book.add(2, Side::Ask, 101, 20, |_, delta| l2_deltas.push(*delta));
+    l3_book.execute_with(
+        1,
+        15,
+        |_, delta| l2_deltas.push(*delta),
+        |anomaly| anomalies.push(*anomaly),
+    );
+    l3_book.cancel_with(
+        2,
+        20,
+        |_, delta| l2_deltas.push(*delta),
+        |anomaly| anomalies.push(*anomaly),
+    );
+
+    let anomalies = anomalies
+        .iter()
+        .map(|a| (a.side, a.px, a.prev_amt, a.amt_delta))
+        .collect::<Vec<_>>();
+    assert_eq!(anomalies, vec![(Side::Ask, 101, 10, -15)]);
+
+    // Only the remaining amount of the order leaves the level
+    assert_eq!(l2_deltas[2].amt_delta, -10);
+    assert_eq!(l2_deltas[2].order_count_delta, -1);
+    assert!(l3_book.order(1).is_none());
+    assert_eq!(l3_book.level_amount(Side::Ask, 101), 0);
+}
@@ -0,0 +1,71 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{L3Delta, Side};
+use lobotomy::common::ByteArrayReader;
+use lobotomy::draft::types::OrderUpdate;
+
+/// SBE layout of `OrderUpdate`, little-endian
+fn order_update(id: i64, px: i64, amt: i64, md_flags: u64, action: u8, entry_type: u8) -> Vec<u8> {
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&id.to_le_bytes());
+    buf.extend_from_slice(&px.to_le_bytes());
+    buf.extend_from_slice(&amt.to_le_bytes());
+    buf.extend_from_slice(&md_flags.to_le_bytes());
+    buf.extend_from_slice(&0_u64.to_le_bytes());
+    buf.extend_from_slice(&42_i32.to_le_bytes());
+    buf.extend_from_slice(&7_u32.to_le_bytes());
+    buf.push(action);
+    buf.push(entry_type);
+    buf
+}
+
+fn decode(buf: &[u8]) -> OrderUpdate {
+    assert_eq!(buf.len(), std::mem::size_of::<OrderUpdate>());
+    ByteArrayReader::new(buf).read_as::<OrderUpdate>()
+}
+
+#[test]
+fn order_update_l3_delta_test() {
+    let new_bid = decode(&order_update(1, 10_050_000, 3, 0x1, 0, b'0'));
+    assert!(matches!(
+        new_bid.l3_delta(),
+        Some(L3Delta::Add {
+            id: 1,
+            side: Side::Bid,
+            px: 10_050_000,
+            amt: 3
+        })
+    ));
+
+    // A change keeps the entry ID
+    let changed_ask = decode(&order_update(2, 10_060_000, 5, 0x1, 1, b'1'));
+    assert!(matches!(
+        changed_ask.l3_delta(),
+        Some(L3Delta::Replace {
+            id: 2,
+            new_id: 2,
+            px: 10_060_000,
+            amt: 5
+        })
+    ));
+
+    let deleted = decode(&order_update(2, 10_060_000, 5, 0x1, 2, b'1'));
+    assert!(matches!(
+        deleted.l3_delta(),
+        Some(L3Delta::Delete { id: 2 })
+    ));
+
+    let empty_book = decode(&order_update(0, 0, 0, 0x1, 2, b'J'));
+    assert!(empty_book.l3_delta().is_none());
+}
+
+#[test]
+fn end_of_transaction_test() {
+    // Flags are combinations, e.g. Day | EndOfTransaction is not a single `MDFlagsSet` variant
+    let last = decode(&order_update(1, 10_050_000, 3, 0x1 | 0x1000, 0, b'0'));
+    assert!(last.is_end_of_transaction());
+    assert_eq!({ last.md_flags }, 0x1001);
+
+    let inner = decode(&order_update(1, 10_050_000, 3, 0x1 | 0x4000, 0, b'0'));
+    assert!(!inner.is_end_of_transaction());
+}
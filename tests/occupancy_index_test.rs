@@ -0,0 +1,162 @@
+extern crate lobotomy;
+
+use lobotomy::order_book::OccupancyIndex;
+use rand::Rng;
+
+use std::collections::BTreeSet;
+
+#[test]
+fn occupancy_index_test() {
+    let len = 300_000;
+    let mut index = OccupancyIndex::new(len);
+    let mut naive = BTreeSet::new();
+
+    assert!(index.is_empty());
+    assert_eq!(index.first(), None);
+    assert_eq!(index.last(), None);
+
+    for _ in 0..100_000 {
+        let idx = rand::thread_rng().gen_range(0..len);
+
+        if rand::thread_rng().gen_bool(0.3) {
+            index.remove(idx);
+            naive.remove(&idx);
+        } else {
+            index.insert(idx);
+            naive.insert(idx);
+        }
+
+        let probe = rand::thread_rng().gen_range(0..len);
+        assert_eq!(index.contains(probe), naive.contains(&probe));
+        assert_eq!(index.next(probe), naive.range(probe + 1..).next().copied());
+        assert_eq!(index.prev(probe), naive.range(..probe).next_back().copied());
+    }
+
+    assert_eq!(index.first(), naive.first().copied());
+    assert_eq!(index.last(), naive.last().copied());
+    assert!(index.iter().eq(naive.iter().copied()));
+    assert!(index.iter_rev().eq(naive.iter().rev().copied()));
+
+    // Growing keeps the bits, shrinking drops the ones beyond the new length
+    index.resize(len * 2);
+    index.insert(len * 2 - 1);
+    assert_eq!(index.last(), Some(len * 2 - 1));
+    index.resize(len / 2);
+    assert!(index.iter().eq(naive.range(..len / 2).copied()));
+
+    index.clear();
+    assert!(index.is_empty());
+    assert_eq!(index.next(0), None);
+}
+
+#[test]
+fn occupancy_index_grow_test() {
+    let mut index = OccupancyIndex::new(0);
+    let mut naive = BTreeSet::new();
+
+    // Upward grows one new high at a time, crossing word and layer boundaries
+    for len in 1..300_000 {
+        index.resize(len);
+
+        if rand::thread_rng().gen_bool(0.01) {
+            index.insert(len - 1);
+            naive.insert(len - 1);
+        }
+
+        if len % 4096 == 0 {
+            let probe = rand::thread_rng().gen_range(0..len);
+            assert_eq!(index.next(probe), naive.range(probe + 1..).next().copied());
+            assert_eq!(index.prev(probe), naive.range(..probe).next_back().copied());
+        }
+    }
+
+    assert!(index.iter().eq(naive.iter().copied()));
+    assert!(index.iter_rev().eq(naive.iter().rev().copied()));
+}
+
+#[test]
+fn occupancy_index_shift_test() {
+    let mut index = OccupancyIndex::new(1000);
+    let mut naive = BTreeSet::new();
+
+    // Downward extensions of various sizes, below and above the page headroom
+    for _ in 0..200 {
+        let shift = rand::thread_rng().gen_range(1..10_000);
+        index.shift(shift);
+        naive = naive.into_iter().map(|idx| idx + shift).collect();
+
+        for _ in 0..20 {
+            let idx = rand::thread_rng().gen_range(0..index.len());
+            index.insert(idx);
+            naive.insert(idx);
+        }
+
+        let probe = rand::thread_rng().gen_range(0..index.len());
+        assert_eq!(index.contains(probe), naive.contains(&probe));
+        assert_eq!(index.next(probe), naive.range(probe + 1..).next().copied());
+        assert_eq!(index.prev(probe), naive.range(..probe).next_back().copied());
+    }
+
+    assert!(index.iter().eq(naive.iter().copied()));
+    assert!(index.iter_rev().eq(naive.iter().rev().copied()));
+
+    let len = index.len() / 3;
+    index.resize(len);
+    assert!(index.iter().eq(naive.range(..len).copied()));
+}
+
+#[test]
+fn occupancy_index_slide_test() {
+    let len = 10_000;
+    let mut index = OccupancyIndex::new(len);
+    let mut naive = BTreeSet::new();
+
+    // A fixed-size window moving both ways, as re-centered by `PriceMap`
+    for _ in 0..500 {
+        let shift = rand::thread_rng().gen_range(-15_000_i64..15_000);
+        let keep = if shift < 0 {
+            0..len.saturating_sub(shift.unsigned_abs() as usize)
+        } else {
+            (shift as usize).min(len)..len
+        };
+
+        for idx in naive.iter().filter(|idx| !keep.contains(*idx)) {
+            index.remove(*idx);
+        }
+
+        if shift < 0 {
+            let shift = shift.unsigned_abs() as usize;
+            index.shift(shift);
+            index.resize(len);
+            naive = naive
+                .into_iter()
+                .filter(|idx| keep.contains(idx))
+                .map(|idx| idx + shift)
+                .collect();
+        } else {
+            let count = shift as usize;
+            index.drop_front(count.min(len));
+            index.resize(len);
+            naive = naive
+                .into_iter()
+                .filter(|idx| keep.contains(idx))
+                .map(|idx| idx - count)
+                .collect();
+        }
+
+        for _ in 0..20 {
+            let idx = rand::thread_rng().gen_range(0..len);
+            index.insert(idx);
+            naive.insert(idx);
+        }
+
+        let probe = rand::thread_rng().gen_range(0..len);
+        assert_eq!(index.len(), len);
+        assert_eq!(index.contains(probe), naive.contains(&probe));
+        assert_eq!(index.next(probe), naive.range(probe + 1..).next().copied());
+        assert_eq!(index.prev(probe), naive.range(..probe).next_back().copied());
+    }
+
+    assert!(index.iter().eq(naive.iter().copied()));
+    assert!(index.iter_rev().eq(naive.iter().rev().copied()));
+}
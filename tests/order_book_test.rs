@@ -0,0 +1,301 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{L2Delta, L3Delta, Level, Side};
+use lobotomy::common::Decimal;
+use lobotomy::order_book::{
+    ArrayLevels, BookState, BucketGrid, BucketLadder, ChecksumValue, ConflatedBook, KrakenChecksum,
+    OkxChecksum, OrderBook, OrderStore, PagedLevels,
+};
+
+#[test]
+fn order_book_test() {
+    let mut lob = OrderBook::<f64, f64, 4>::new(0.0, None, 0.5, None);
+    assert_eq!(lob.state(), BookState::Empty);
+    assert!(lob.mid().is_none());
+
+    lob.apply_l2_snapshot(&[Level::new(100.0, 3.0)], &[]);
+    assert_eq!(lob.state(), BookState::OneSided);
+
+    lob.apply_l2_upserts(&[], &[Level::new(101.0, 1.0)]);
+    assert_eq!(lob.state(), BookState::Normal);
+    assert_eq!(lob.best_bid(), Some(Level::new(100.0, 3.0)));
+    assert_eq!(lob.best_ask(), Some(Level::new(101.0, 1.0)));
+    assert_eq!(lob.spread(), Some(1.0));
+    assert_eq!(lob.spread_ticks(), Some(2));
+    assert_eq!(lob.mid(), Some(100.5));
+    // Leans towards the thinner ask
+    assert_eq!(lob.microprice(), Some(100.75));
+    assert_eq!(lob.depth_weighted_mid(1.0), Some(100.5));
+    assert_eq!(lob.depth_weighted_mid(2.0), None);
+
+    lob.apply_l2_deltas(&[], &[L2Delta::new(100.0, 2.0)]);
+    assert_eq!(lob.state(), BookState::Locked);
+
+    lob.apply_l2_deltas(&[], &[L2Delta::new(99.5, 2.0)]);
+    assert_eq!(lob.state(), BookState::Crossed);
+    assert_eq!(lob.spread_ticks(), Some(-1));
+
+    lob.clear();
+    assert_eq!(lob.state(), BookState::Empty);
+}
+
+#[test]
+fn integer_analytics_test() {
+    // Price mantissas and integer amounts, e.g. SIMBA or ITCH shares
+    let mut lob = OrderBook::<i64, u32, 4>::new(0, None, 5, None);
+    lob.apply_l2_snapshot(
+        &[Level::new(100, 3), Level::new(95, 2)],
+        &[Level::new(110, 1), Level::new(115, 4)],
+    );
+
+    assert_eq!(lob.spread(), Some(10.0));
+    assert_eq!(lob.mid(), Some(105.0));
+    assert_eq!(lob.microprice(), Some(107.5));
+    assert_eq!(lob.depth_weighted_mid(2), Some(106.25));
+
+    let fill = lob.ask().cost_to_fill(2).unwrap();
+    assert_eq!(fill.avg_px, 112.5);
+    assert_eq!(fill.worst_px, 115);
+    assert_eq!(lob.bid().amount_within_bps(500.0), 5);
+}
+
+#[test]
+fn spread_through_zero_test() {
+    // Calendar spread quoted around zero, with and without the bounded window
+    for max_ticks in [None, Some(64)] {
+        let mut lob = OrderBook::<f64, f64, 2>::new(0.0, None, 0.25, max_ticks);
+
+        lob.apply_l2_snapshot(
+            &[
+                Level::new(-0.5, 1.0),
+                Level::new(-1.0, 2.0),
+                Level::new(-3.0, 3.0),
+            ],
+            &[Level::new(-0.25, 1.0), Level::new(0.5, 2.0)],
+        );
+        assert_eq!(lob.state(), BookState::Normal);
+        assert_eq!(lob.spread_ticks(), Some(1));
+        assert_eq!(lob.mid(), Some(-0.375));
+
+        // The market trades through zero
+        lob.apply_l2_upserts(
+            &[Level::new(0.25, 4.0)],
+            &[Level::new(-0.25, 0.0), Level::new(0.75, 1.0)],
+        );
+        assert_eq!(lob.best_bid(), Some(Level::new(0.25, 4.0)));
+        assert_eq!(lob.best_ask(), Some(Level::new(0.5, 2.0)));
+        assert_eq!(lob.spread_ticks(), Some(1));
+
+        // Deleting the top refills from below zero
+        lob.apply_l2_deltas(&[L2Delta::new(0.25, -4.0)], &[]);
+        assert_eq!(
+            lob.bid().book().levels(),
+            &[Level::new(-0.5, 1.0), Level::new(-1.0, 2.0)]
+        );
+
+        // A new low far below the start of the range
+        lob.apply_l2_upserts(&[Level::new(-0.5, 0.0), Level::new(-1.0, 0.0)], &[]);
+        assert_eq!(lob.best_bid(), Some(Level::new(-3.0, 3.0)));
+        assert_eq!(lob.spread_ticks(), Some(14));
+    }
+}
+
+#[test]
+fn custom_storage_test() {
+    type Book = OrderBook<f64, f64, 2, PagedLevels<f64, 64>, ArrayLevels<f64, f64, 2>>;
+
+    let lob = Book::new(0.0, None, 0.5, None);
+    let mut lob = ConflatedBook::new(
+        lob.with_observers(
+            BucketLadder::new(BucketGrid::Ticks(4), 0.5),
+            BucketLadder::new(BucketGrid::Ticks(4), 0.5),
+        ),
+        4,
+    );
+    lob.apply_l2_snapshot(
+        &[
+            Level::new(100.0, 1.0),
+            Level::new(99.5, 2.0),
+            Level::new(99.0, 3.0),
+        ],
+        &[Level::new(101.0, 1.0)],
+    );
+    // Within the depth of the ask side
+    lob.push(&Side::Ask, &L2Delta::new(101.5, 4.0));
+    assert!(lob.commit());
+
+    let book = lob.book();
+    assert_eq!(book.state(), BookState::Normal);
+    assert_eq!(book.bid().book().levels().len(), 2);
+    assert_eq!(book.bid().get_level(99.0).amt, 3.0);
+    assert_eq!(book.bid().observer().get(49).amt, 5.0);
+    assert_eq!(book.ask().observer().get(50).amt, 5.0);
+}
+
+#[test]
+fn l3_deltas_across_sides_test() {
+    let mut lob = OrderBook::<f64, f64, 4>::new(0.0, None, 0.5, None);
+    let mut orders = OrderStore::new(16);
+
+    // One slice: a duplicate add moves the order to the other side, then its ID is reused
+    lob.apply_l3_deltas(
+        &mut orders,
+        &[
+            L3Delta::Add {
+                id: 1,
+                side: Side::Bid,
+                px: 100.0,
+                amt: 10.0,
+            },
+            L3Delta::Add {
+                id: 1,
+                side: Side::Ask,
+                px: 101.0,
+                amt: 5.0,
+            },
+            L3Delta::Add {
+                id: 2,
+                side: Side::Ask,
+                px: 102.0,
+                amt: 4.0,
+            },
+            L3Delta::Delete { id: 1 },
+            L3Delta::Add {
+                id: 1,
+                side: Side::Bid,
+                px: 99.0,
+                amt: 3.0,
+            },
+            L3Delta::Reduce { id: 1, amt: 1.0 },
+        ],
+    );
+
+    assert_eq!(
+        lob.bid().book().levels(),
+        &[Level {
+            px: 99.0,
+            amt: 2.0,
+            order_count: 1,
+        }]
+    );
+    assert_eq!(
+        lob.ask().book().levels(),
+        &[Level {
+            px: 102.0,
+            amt: 4.0,
+            order_count: 1,
+        }]
+    );
+    assert_eq!(lob.bid().get_level(100.0).order_count, 0);
+    assert_eq!(lob.ask().get_level(101.0).amt, 0.0);
+    assert_eq!(orders.get(1).unwrap().side, Side::Bid);
+}
+
+#[test]
+fn conflated_book_test() {
+    let mut lob = ConflatedBook::new(OrderBook::<f64, f64, 4>::new(0.0, None, 0.5, None), 4);
+    lob.apply_l2_snapshot(&[Level::new(100.0, 1.0)], &[Level::new(101.0, 1.0)]);
+
+    // A sweep: the aggressive ask rests only after taking the bid
+    assert!(!lob.start_at(1));
+    lob.push(
+        &Side::Ask,
+        &L2Delta {
+            px: 99.5,
+            amt_delta: 2.0,
+            order_count_delta: 1,
+        },
+    );
+    assert!(!lob.start_at(1));
+    lob.push(&Side::Bid, &L2Delta::new(100.0, -1.0));
+    assert!(lob.has_pending());
+    assert_eq!(lob.book().best_ask().unwrap().px, 101.0);
+
+    assert!(lob.start_at(2));
+    assert!(!lob.has_pending());
+    assert_eq!(lob.book().state(), BookState::OneSided);
+    assert_eq!(lob.book().best_ask().unwrap().px, 99.5);
+    assert!(lob.book().bid().best_px_changed());
+    assert!(lob.book().ask().best_px_changed());
+
+    // Netted out, nothing to report
+    for amt_delta in [1.0, -1.0] {
+        lob.push(&Side::Ask, &L2Delta::new(99.5, amt_delta));
+    }
+    assert!(!lob.commit());
+    assert!(lob.book().ask().top_changes().is_empty());
+    assert!(!lob.commit());
+
+    // Snapshots supersede pending updates
+    lob.push(&Side::Bid, &L2Delta::new(99.0, 1.0));
+    lob.apply_l2_snapshot(&[], &[]);
+    assert!(!lob.has_pending());
+    assert!(!lob.commit());
+    assert_eq!(lob.book().state(), BookState::Empty);
+}
+
+#[test]
+fn checksum_test() {
+    // Kraken strips the decimal point and leading zeros: "123456789"
+    let mut lob = OrderBook::<f64, f64, 4>::new(0.0, None, 0.1, None);
+    lob.apply_l2_snapshot(&[], &[Level::new(1234.5, 6.789)]);
+    let mut kraken = KrakenChecksum::new(10, 1, 3);
+    assert_eq!(lob.checksum(&mut kraken), 0xCBF43926);
+
+    let mut resyncs = Vec::new();
+    assert!(lob.verify_checksum(&mut kraken, 0xCBF43926, |computed| resyncs.push(computed)));
+    assert!(!lob.verify_checksum(&mut kraken, 1, |computed| resyncs.push(computed)));
+    assert_eq!(resyncs, vec![0xCBF43926]);
+
+    // OKX interleaves the sides and drops trailing zeros, the example from its docs
+    type D8 = Decimal<8>;
+    let level =
+        |px: &str, amt: &str| Level::new(px.parse::<D8>().unwrap(), amt.parse::<D8>().unwrap());
+    let mut lob = OrderBook::<D8, D8, 4>::new(D8::ZERO, None, "0.1".parse::<D8>().unwrap(), None);
+    lob.apply_l2_snapshot(
+        &[level("3366.1", "7"), level("3366", "6")],
+        &[level("3366.8", "9"), level("3368", "8")],
+    );
+    let mut okx = OkxChecksum::new(25, 1, 0);
+    assert_eq!(
+        lob.checksum(&mut okx),
+        crc32fast::hash(b"3366.1:7:3366.8:9:3366:6:3368:8")
+    );
+    assert_eq!(
+        lob.checksum(&mut OkxChecksum::new(1, 1, 0)),
+        crc32fast::hash(b"3366.1:7:3366.8:9")
+    );
+
+    let fixed = |value: &dyn Fn(&mut String)| {
+        let mut out = String::new();
+        value(&mut out);
+        out
+    };
+    assert_eq!(
+        fixed(&|out| "0.05".parse::<D8>().unwrap().write_fixed(5, out)),
+        "0.05000"
+    );
+    assert_eq!(
+        fixed(&|out| "-1.23456789".parse::<D8>().unwrap().write_fixed(2, out)),
+        "-1.23"
+    );
+    // Rounds half away from zero like f64
+    assert_eq!(
+        fixed(&|out| "-1.23556789".parse::<D8>().unwrap().write_fixed(2, out)),
+        "-1.24"
+    );
+    assert_eq!(
+        fixed(&|out| "0.995".parse::<D8>().unwrap().write_fixed(2, out)),
+        "1.00"
+    );
+    assert_eq!(
+        fixed(&|out| "-0.004".parse::<D8>().unwrap().write_fixed(2, out)),
+        "0.00"
+    );
+    // More decimals than 10^x fits in u64
+    assert_eq!(
+        fixed(&|out| Decimal::<2>::from_mantissa(150).write_fixed(22, out)),
+        "1.5000000000000000000000"
+    );
+    assert_eq!(fixed(&|out| 0.05_f64.write_fixed(3, out)), "0.050");
+}
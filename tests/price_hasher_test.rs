@@ -0,0 +1,45 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::Level;
+use lobotomy::common::TickSchedule;
+use lobotomy::order_book::{L2BookBuilder, PriceHasher, RangeGrowth};
+
+#[test]
+fn range_growth_test() {
+    // Integer prices in exchange units, tick size of 5
+    let tick_schedule = TickSchedule::new(5_i64);
+
+    let mut hasher = PriceHasher::new(1_000, tick_schedule).with_growth(RangeGrowth::Ticks(100));
+    assert_eq!(hasher.hash(&1_005), (1, 0));
+    // 20 ticks below the start plus 100 ticks of growth
+    assert_eq!(hasher.hash(&900), (100, 120));
+    assert_eq!(hasher.idx_to_px(&0), 400);
+    assert_eq!(hasher.try_hash(&395), None);
+
+    let mut hasher = PriceHasher::new(1_000_000, tick_schedule);
+    // 10% of 180000 ticks
+    assert_eq!(hasher.hash(&900_000), (18_000, 38_000));
+
+    // At least a few ticks around zero
+    let mut hasher = PriceHasher::new(0, tick_schedule).with_growth(RangeGrowth::Percent(10));
+    assert_eq!(hasher.hash(&-10), (64, 66));
+}
+
+#[test]
+fn integer_price_book_test() {
+    let mut ask = L2BookBuilder::<i64, f64, 2, false>::new(1_000, None, 5, None)
+        .with_growth(RangeGrowth::Ticks(16));
+
+    ask.apply_l2_upserts(&[
+        Level::new(1_010, 1.0),
+        Level::new(7, 2.0),
+        Level::new(-3, 3.0),
+    ]);
+
+    // Prices are rounded to the tick and stay exact through the range growth
+    assert_eq!(
+        ask.book().levels(),
+        &[Level::new(-5, 3.0), Level::new(5, 2.0)]
+    );
+    assert_eq!(ask.get_level(1_010).amt, 1.0);
+}
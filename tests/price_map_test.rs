@@ -1,9 +1,10 @@
 extern crate lobotomy;
 
-use lobotomy::order_book::PriceMap;
+use lobotomy::common::types::Level;
+use lobotomy::order_book::{DenseLevels, LevelStorage, PagedLevels, PriceLevel, PriceMap};
 use rand::Rng;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[test]
 fn price_map_test() {
@@ -30,6 +31,7 @@ fn price_map_test() {
         round_to_tick(rand::thread_rng().gen_range(px_range)),
         None,
         tick_size,
+        None,
     );
     let mut naive_map = HashMap::<usize, f64>::new();
 
@@ -42,3 +44,175 @@ fn price_map_test() {
         assert_eq!(fast_map.get_immut(*tick_idx as f64 * tick_size).amt, *amt);
     }
 }
+
+#[test]
+fn paged_price_map_test() {
+    let tick_size = 0.01;
+
+    let mut dense = PriceMap::<f64, f64, DenseLevels<f64>>::new(100.0, None, tick_size, None);
+    let mut paged = PriceMap::<f64, f64, PagedLevels<f64, 64>>::new(100.0, None, tick_size, None);
+
+    // Prices below the start trigger downward extensions
+    for _ in 0..100_000 {
+        let px = (rand::thread_rng().gen_range(1.0..200.0) / tick_size).round() * tick_size;
+        let amt = if rand::thread_rng().gen_bool(0.3) {
+            0.0
+        } else {
+            rand::thread_rng().gen_range(1.0..100.0)
+        };
+
+        dense.get_mut(px).amt = amt;
+        paged.get_mut(px).amt = amt;
+
+        assert_eq!(dense.get_immut(px).amt, paged.get_immut(px).amt);
+        assert_eq!(dense.next_px::<true>(&px), paged.next_px::<true>(&px));
+        assert_eq!(dense.next_px::<false>(&px), paged.next_px::<false>(&px));
+    }
+
+    let top = |top: [Option<(f64, PriceLevel<f64>)>; 16]| {
+        top.map(|lvl| lvl.map(|(px, lvl)| (px, lvl.amt)))
+    };
+    assert_eq!(
+        top(dense.top_levels::<16, true>()),
+        top(paged.top_levels::<16, true>())
+    );
+    assert_eq!(
+        top(dense.top_levels::<16, false>()),
+        top(paged.top_levels::<16, false>())
+    );
+}
+
+#[test]
+fn negative_prices_test() {
+    let tick_size = 0.01;
+    let mut fast_map = PriceMap::<f64, f64>::new(0.0, None, tick_size, None);
+    let mut naive_map = BTreeMap::<i64, f64>::new();
+
+    for _ in 0..100_000 {
+        let tick_idx = rand::thread_rng().gen_range(-10_000..10_000);
+        let amt = if rand::thread_rng().gen_bool(0.3) {
+            0.0
+        } else {
+            rand::thread_rng().gen_range(1.0..100.0)
+        };
+
+        fast_map.get_mut(tick_idx as f64 * tick_size).amt = amt;
+        if amt == 0.0 {
+            naive_map.remove(&tick_idx);
+        } else {
+            naive_map.insert(tick_idx, amt);
+        }
+
+        let probe = rand::thread_rng().gen_range(-12_000..12_000);
+        let next = fast_map
+            .next_px::<false>(&(probe as f64 * tick_size))
+            .map(|lvl| ((lvl.px / tick_size).round() as i64, lvl.amt));
+        assert_eq!(
+            next,
+            naive_map.range(probe + 1..).next().map(|(t, a)| (*t, *a))
+        );
+        let prev = fast_map
+            .next_px::<true>(&(probe as f64 * tick_size))
+            .map(|lvl| ((lvl.px / tick_size).round() as i64, lvl.amt));
+        assert_eq!(
+            prev,
+            naive_map.range(..probe).next_back().map(|(t, a)| (*t, *a))
+        );
+    }
+
+    for (tick_idx, amt) in naive_map.iter() {
+        assert_eq!(fast_map.get_immut(*tick_idx as f64 * tick_size).amt, *amt);
+    }
+}
+
+#[test]
+fn level_iter_test() {
+    let tick_size = 0.01;
+    // A narrow window, so the walk crosses the overflow on both ends
+    let mut fast_map = PriceMap::<f64, f64>::new(0.0, None, tick_size, Some(256));
+    let mut naive_map = BTreeMap::<i64, f64>::new();
+
+    for step in 0..20_000 {
+        let tick_idx = rand::thread_rng().gen_range(-2_000..2_000);
+        let amt = if rand::thread_rng().gen_bool(0.3) {
+            0.0
+        } else {
+            rand::thread_rng().gen_range(1.0..100.0)
+        };
+
+        fast_map.get_mut(tick_idx as f64 * tick_size).amt = amt;
+        if amt == 0.0 {
+            naive_map.remove(&tick_idx);
+        } else {
+            naive_map.insert(tick_idx, amt);
+        }
+
+        if step % 1_000 == 0 {
+            fast_map.recenter(tick_idx as f64 * tick_size);
+        }
+    }
+
+    let levels = |iter: &mut dyn Iterator<Item = Level<f64, f64>>| {
+        iter.map(|lvl| ((lvl.px / tick_size).round() as i64, lvl.amt))
+            .collect::<Vec<_>>()
+    };
+    assert!(fast_map.overflow_len() > 0);
+    assert_eq!(
+        levels(&mut fast_map.iter::<false>()),
+        naive_map.iter().map(|(t, a)| (*t, *a)).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        levels(&mut fast_map.iter::<true>()),
+        naive_map
+            .iter()
+            .rev()
+            .map(|(t, a)| (*t, *a))
+            .collect::<Vec<_>>()
+    );
+}
+
+fn check_recenter<S: LevelStorage<f64>>() {
+    let tick_size = 0.01;
+    let mut fast_map = PriceMap::<f64, f64, S>::new(100.0, None, tick_size, Some(1_000));
+    let mut naive_map = BTreeMap::<i64, f64>::new();
+    let mut top_tick_idx = 10_000_i64;
+
+    // The top drifts both ways, sometimes jumping past the whole window
+    for step in 0..50_000 {
+        top_tick_idx += if step % 5_000 == 0 {
+            rand::thread_rng().gen_range(-5_000..5_000)
+        } else {
+            rand::thread_rng().gen_range(-5..=5)
+        };
+
+        let tick_idx = top_tick_idx + rand::thread_rng().gen_range(-600..600);
+        let amt = if rand::thread_rng().gen_bool(0.3) {
+            0.0
+        } else {
+            rand::thread_rng().gen_range(1.0..100.0)
+        };
+
+        fast_map.get_mut(tick_idx as f64 * tick_size).amt = amt;
+        if amt == 0.0 {
+            naive_map.remove(&tick_idx);
+        } else {
+            naive_map.insert(tick_idx, amt);
+        }
+
+        fast_map.recenter(top_tick_idx as f64 * tick_size);
+    }
+
+    for (tick_idx, amt) in naive_map.iter() {
+        assert_eq!(fast_map.get_immut(*tick_idx as f64 * tick_size).amt, *amt);
+    }
+    assert!(fast_map
+        .iter::<false>()
+        .map(|lvl| ((lvl.px / tick_size).round() as i64, lvl.amt))
+        .eq(naive_map.iter().map(|(t, a)| (*t, *a))));
+}
+
+#[test]
+fn recenter_test() {
+    check_recenter::<DenseLevels<f64>>();
+    check_recenter::<PagedLevels<f64, 64>>();
+}
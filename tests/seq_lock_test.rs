@@ -0,0 +1,60 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::{L2Delta, Level};
+use lobotomy::common::SeqLock;
+use lobotomy::order_book::{OrderBook, TopOfBook};
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn seq_lock_test() {
+    let lock = SeqLock::new([0_u64; 32]);
+    let done = AtomicBool::new(false);
+
+    std::thread::scope(|s| {
+        for _ in 0..3 {
+            s.spawn(|| {
+                let mut last = 0;
+                while !done.load(Ordering::Acquire) {
+                    let value = lock.read();
+                    // Never torn, never going back
+                    assert!(value.iter().all(|x| *x == value[0]));
+                    assert!(value[0] >= last);
+                    last = value[0];
+                }
+            });
+        }
+
+        for x in 1..=100_000 {
+            lock.write(&[x; 32]);
+        }
+        done.store(true, Ordering::Release);
+    });
+
+    assert_eq!(lock.read(), [100_000; 32]);
+    assert_eq!(lock.seq(), 200_000);
+    assert_eq!(std::mem::align_of::<SeqLock<u8>>(), 64);
+}
+
+#[test]
+fn publish_top_test() {
+    let mut lob = OrderBook::<f64, f64, 4>::new(0.0, None, 0.5, None);
+    let top = SeqLock::new(TopOfBook::<f64, f64, 2>::default());
+    assert!(top.read().best_bid().is_none());
+
+    let level = |px, amt| Level::new(px, amt);
+    lob.apply_l2_snapshot(
+        &[level(100.0, 1.0), level(99.5, 2.0), level(99.0, 3.0)],
+        &[level(101.0, 4.0)],
+    );
+    lob.publish_top(&top);
+
+    let snapshot = top.read();
+    assert_eq!(snapshot.bids(), &[level(100.0, 1.0), level(99.5, 2.0)]);
+    assert_eq!(snapshot.asks(), &[level(101.0, 4.0)]);
+
+    lob.apply_l2_deltas(&[L2Delta::new(100.0, -1.0)], &[]);
+    lob.publish_top(&top);
+    assert_eq!(top.read().best_bid(), Some(level(99.5, 2.0)));
+    assert_eq!(top.seq(), 4);
+}
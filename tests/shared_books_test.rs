@@ -0,0 +1,116 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::Level;
+use lobotomy::order_book::{OrderBook, SharedBooksReader, SharedBooksWriter, TopOfBook};
+
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const PATH_VAR: &str = "LOBOTOMY_SHARED_BOOKS_PATH";
+
+fn level(px: f64, amt: f64) -> Level<f64, f64> {
+    Level::new(px, amt)
+}
+
+#[test]
+fn shared_books_test() {
+    let path = std::env::temp_dir().join(format!("shared_books_test_{}", std::process::id()));
+    let writer = SharedBooksWriter::<f64, f64, 2>::create(&path, 2).unwrap();
+
+    let mut lob = OrderBook::<f64, f64, 4>::new(0.0, None, 0.5, None);
+    lob.apply_l2_snapshot(
+        &[level(100.0, 1.0), level(99.5, 2.0), level(99.0, 3.0)],
+        &[level(101.0, 4.0)],
+    );
+    writer.publish_book(1, &lob);
+
+    let reader = SharedBooksReader::<f64, f64, 2>::open(&path).unwrap();
+    assert_eq!(reader.instrument_count(), 2);
+    assert!(reader.read(0).best_bid().is_none());
+    assert_eq!(
+        reader.read(1).bids(),
+        &[level(100.0, 1.0), level(99.5, 2.0)]
+    );
+    assert_eq!(reader.read(1).asks(), &[level(101.0, 4.0)]);
+    assert_eq!(reader.seq(1), 2);
+
+    // Other depths and types are rejected
+    assert!(SharedBooksReader::<f64, f64, 4>::open(&path).is_err());
+    assert!(SharedBooksReader::<i64, u32, 2>::open(&path).is_err());
+    // Same sizes, told apart by the type tags
+    assert!(SharedBooksReader::<i64, i64, 2>::open(&path).is_err());
+
+    // Another process attaches while the books keep changing
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "shared_books_reader_process",
+            "--ignored",
+            "--nocapture",
+        ])
+        .env(PATH_VAR, &path)
+        .spawn()
+        .unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let mut amt = 1.0;
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        assert!(Instant::now() < deadline, "Reader process is stuck");
+
+        amt += 1.0;
+        writer.publish(
+            0,
+            &TopOfBook::new(&[level(100.0, amt)], &[level(101.0, amt)]),
+        );
+    };
+    assert!(status.success());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Runs as the second process of `shared_books_test`
+#[test]
+#[ignore]
+fn shared_books_reader_process() {
+    let path = std::env::var(PATH_VAR).expect("Spawned by shared_books_test");
+    let reader = SharedBooksReader::<f64, f64, 2>::open(path).unwrap();
+    assert_eq!(reader.read(1).best_bid(), Some(level(100.0, 1.0)));
+
+    let deadline = Instant::now() + Duration::from_secs(20);
+    let mut last_amt = 0.0;
+    while reader.seq(0) < 20_000 {
+        assert!(Instant::now() < deadline, "Writer process is stuck");
+
+        // Never torn, never going back
+        let top = reader.read(0);
+        if let (Some(bid), Some(ask)) = (top.best_bid(), top.best_ask()) {
+            assert_eq!(bid.amt, ask.amt);
+            assert!(bid.amt >= last_amt);
+            last_amt = bid.amt;
+        }
+    }
+}
+
+#[test]
+fn shared_books_recreate_test() {
+    let path =
+        std::env::temp_dir().join(format!("shared_books_recreate_test_{}", std::process::id()));
+    let writer = SharedBooksWriter::<f64, f64, 2>::create(&path, 4).unwrap();
+    writer.publish(3, &TopOfBook::new(&[level(100.0, 1.0)], &[]));
+    let old_reader = SharedBooksReader::<f64, f64, 2>::open(&path).unwrap();
+
+    // A smaller file replaces the mapped one, the old mapping stays readable
+    drop(writer);
+    let writer = SharedBooksWriter::<f64, f64, 2>::create(&path, 1).unwrap();
+    assert_eq!(old_reader.read(3).best_bid(), Some(level(100.0, 1.0)));
+
+    writer.publish(0, &TopOfBook::new(&[level(99.0, 2.0)], &[]));
+    let reader = SharedBooksReader::<f64, f64, 2>::open(&path).unwrap();
+    assert_eq!(reader.instrument_count(), 1);
+    assert_eq!(reader.read(0).best_bid(), Some(level(99.0, 2.0)));
+
+    std::fs::remove_file(&path).unwrap();
+}
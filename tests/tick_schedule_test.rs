@@ -0,0 +1,62 @@
+extern crate lobotomy;
+
+use lobotomy::common::types::Level;
+use lobotomy::common::TickSchedule;
+use lobotomy::nasdaq::Price4Wrapper;
+use lobotomy::order_book::L2BookBuilder;
+
+#[test]
+fn tick_schedule_test() {
+    let schedule = TickSchedule::with_bands(&[(0.0, 0.0001), (1.0, 0.01), (100.0, 0.1)]);
+
+    assert_eq!(schedule.px_to_tick_idx(&0.9999), 9_999);
+    assert_eq!(schedule.px_to_tick_idx(&1.0), 10_000);
+    assert_eq!(schedule.px_to_tick_idx(&1.01), 10_001);
+    assert_eq!(schedule.px_to_tick_idx(&100.1), 19_901);
+    assert_eq!(schedule.tick_size(&0.5), 0.0001);
+    assert_eq!(schedule.tick_size(&50.0), 0.01);
+    assert_eq!(schedule.round_to_tick_size(&1.234), 1.23);
+    assert_eq!(schedule.round_to_tick_size(&0.12344), 0.1234);
+
+    // Indices are continuous and monotonic across the bands
+    let mut prev_px = schedule.tick_idx_to_px(&9_000);
+    for tick_idx in 9_001..20_000 {
+        let px = schedule.tick_idx_to_px(&tick_idx);
+        assert!(px > prev_px);
+        assert_eq!(schedule.px_to_tick_idx(&px), tick_idx);
+        prev_px = px;
+    }
+
+    // A constant tick size is a single band
+    let constant = TickSchedule::from(0.5);
+    assert_eq!(constant.px_to_tick_idx(&-1.0), -2);
+    assert_eq!(constant.tick_idx_to_px(&3), 1.5);
+}
+
+#[test]
+fn sub_dollar_stock_test() {
+    let px = |raw: u32| Price4Wrapper(itchy::Price4::from(raw));
+    let mut bid = L2BookBuilder::<Price4Wrapper, u32, 2, true>::new(
+        px(0),
+        None,
+        Price4Wrapper::tick_schedule(),
+        None,
+    );
+
+    bid.apply_l2_upserts(&[
+        Level::new(px(10_100), 100),
+        Level::new(px(9_999), 200),
+        Level::new(px(9_998), 300),
+    ]);
+    assert_eq!(
+        bid.book().levels(),
+        &[Level::new(px(10_100), 100), Level::new(px(9_999), 200)]
+    );
+
+    // Sub-dollar prices keep their 4th decimal, the refill crosses the band boundary
+    bid.apply_l2_upserts(&[Level::new(px(10_100), 0)]);
+    assert_eq!(
+        bid.book().levels(),
+        &[Level::new(px(9_999), 200), Level::new(px(9_998), 300)]
+    );
+}